@@ -0,0 +1,186 @@
+use crate::issuer_key::{ed25519_key_from_secret, sign_with_key};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use did_key::KeyMaterial;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Lifetime of an access token in seconds.
+pub const ACCESS_TOKEN_TTL_SECONDS: i64 = 86400;
+
+/// Lifetime of a refresh token in days.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Scope a wallet requests at the token endpoint to receive a refresh token.
+pub const OFFLINE_ACCESS_SCOPE: &str = "offline_access";
+
+/// JWT `typ` of access tokens (RFC 9068).
+const ACCESS_TOKEN_TYPE: &str = "at+jwt";
+
+/// Confirmation claim binding the token to the key of a DPoP proof (RFC 9449, section 6).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Confirmation {
+    pub jkt: String,
+}
+
+/// Claims of a self-contained access token, any issuer instance can validate it without shared state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub aud: String,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub certificate_ids: Vec<String>,
+    pub c_nonce_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+}
+
+impl AccessTokenClaims {
+    pub fn new(
+        issuer_url: &str,
+        certificate_ids: Vec<String>,
+        c_nonce: &str,
+        dpop_jkt: Option<String>,
+    ) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            iss: issuer_url.to_string(),
+            aud: issuer_url.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            iat: now,
+            exp: now + ACCESS_TOKEN_TTL_SECONDS,
+            certificate_ids,
+            c_nonce_hash: URL_SAFE_NO_PAD.encode(Sha256::digest(c_nonce.as_bytes())),
+            cnf: dpop_jkt.map(|jkt| Confirmation { jkt }),
+        }
+    }
+}
+
+/// Response of the token introspection endpoint (RFC 7662).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TokenIntrospection {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub certificate_ids: Vec<String>,
+    /// Number of credentials already requested with the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+impl TokenIntrospection {
+    pub fn inactive() -> Self {
+        Self::default()
+    }
+}
+
+/// Token request with `grant_type=refresh_token` (RFC 6749, section 6).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RefreshTokenRequest {
+    pub grant_type: String,
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IntrospectionRequest {
+    pub token: String,
+}
+
+/// Whether an access token is a self-contained JWT rather than an opaque handle.
+pub fn is_jwt_access_token(access_token: &str) -> bool {
+    access_token.split('.').count() == 3
+}
+
+/// Signs the access token with the issuer's Ed25519 key, derived from its `private_key`.
+pub fn encode_access_token(private_key: &str, claims: &AccessTokenClaims) -> Result<String> {
+    sign_with_key(
+        &ed25519_key_from_secret(private_key),
+        ACCESS_TOKEN_TYPE,
+        claims,
+    )
+}
+
+/// Validates signature, expiry and audience of an access token issued by this issuer.
+pub fn decode_access_token(
+    access_token: &str,
+    private_key: &str,
+    issuer_url: &str,
+) -> Result<AccessTokenClaims> {
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[issuer_url]);
+    validation.set_issuer(&[issuer_url]);
+
+    let key = DecodingKey::from_ed_der(&ed25519_key_from_secret(private_key).public_key_bytes());
+    let claims = decode::<AccessTokenClaims>(access_token, &key, &validation)?.claims;
+    if claims.certificate_ids.is_empty() {
+        return Err(anyhow!("Access token does not grant any certificate"));
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "this-is-a-test-issuer-key";
+    const ISSUER_URL: &str = "http://localhost:3000";
+
+    fn claims() -> AccessTokenClaims {
+        AccessTokenClaims::new(ISSUER_URL, vec!["certificate-1".to_string()], "nonce", None)
+    }
+
+    #[test]
+    fn test_access_token_roundtrip() {
+        let claims = claims();
+        let access_token = encode_access_token(PRIVATE_KEY, &claims).unwrap();
+        assert!(is_jwt_access_token(&access_token));
+
+        let decoded = decode_access_token(&access_token, PRIVATE_KEY, ISSUER_URL).unwrap();
+        assert_eq!(decoded.certificate_ids, vec!["certificate-1".to_string()]);
+        assert_eq!(decoded.jti, claims.jti);
+
+        let mut tampered = access_token.clone();
+        tampered.push('x');
+        assert!(decode_access_token(&tampered, PRIVATE_KEY, ISSUER_URL).is_err());
+    }
+
+    #[test]
+    fn test_expired_access_token_is_rejected() {
+        let mut claims = claims();
+        claims.iat -= 2 * ACCESS_TOKEN_TTL_SECONDS;
+        claims.exp = claims.iat + ACCESS_TOKEN_TTL_SECONDS;
+        let access_token = encode_access_token(PRIVATE_KEY, &claims).unwrap();
+
+        assert!(decode_access_token(&access_token, PRIVATE_KEY, ISSUER_URL).is_err());
+    }
+
+    #[test]
+    fn test_access_token_of_another_key_or_issuer_is_rejected() {
+        let access_token = encode_access_token("another-issuer-key", &claims()).unwrap();
+        assert!(decode_access_token(&access_token, PRIVATE_KEY, ISSUER_URL).is_err());
+
+        let access_token = encode_access_token(PRIVATE_KEY, &claims()).unwrap();
+        assert!(
+            decode_access_token(&access_token, PRIVATE_KEY, "https://other.example.com").is_err()
+        );
+    }
+}
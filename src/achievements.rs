@@ -0,0 +1,299 @@
+use crate::config::load_issuer_url;
+use crate::i18n::{languages, resolve_language, translate};
+use crate::issuer_profile::issuer_profile_url;
+use konnektoren_core::game::{Game, GamePath};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// JSON-LD context of Open Badges 3.0 documents.
+pub const OB3_CONTEXT: &str = "https://purl.imsglobal.org/spec/ob/v3p0/context-3.0.2.json";
+
+/// Framework of the language levels achievements are aligned to.
+pub const CEFR_FRAMEWORK: &str = "Common European Framework of Reference for Languages";
+pub const CEFR_URL: &str = "https://www.coe.int/en/web/common-european-framework-reference-languages/table-1-cefr-3.3-common-reference-levels-global-scale";
+
+/// Result type of scores, which are given in percent from 0 to 100.
+pub const PERCENT_RESULT_TYPE: &str = "Percent";
+
+/// Curated metadata of a game path that konnektoren-core does not know about.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct GamePathMetadata {
+    level: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChallengeSummary {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// An Open Badges achievement, one per konnektoren-core game path.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AchievementDefinition {
+    pub slug: String,
+    pub game_path_id: String,
+    pub name: String,
+    /// CEFR level of the game path, e.g. `A2`.
+    pub level: Option<String>,
+    pub tags: Vec<String>,
+    pub challenges: Vec<ChallengeSummary>,
+}
+
+lazy_static! {
+    static ref ACHIEVEMENTS: Vec<AchievementDefinition> = {
+        let metadata: HashMap<String, GamePathMetadata> =
+            serde_json::from_str(include_str!("../assets/achievements.json")).unwrap();
+        Game::default()
+            .game_paths
+            .iter()
+            .map(|game_path| AchievementDefinition::from_game_path(game_path, &metadata))
+            .collect()
+    };
+}
+
+/// Turns a game path id or name into a stable URL segment.
+pub fn slugify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// URL at which the issuer publishes the achievement with the given slug.
+pub fn achievement_url(slug: &str) -> String {
+    format!(
+        "{}/ob3/achievements/{}",
+        load_issuer_url().trim_end_matches('/'),
+        slug
+    )
+}
+
+/// Result description of the overall score of an achievement, optionally with the score needed to pass.
+pub fn score_result_description(
+    achievement_id: &str,
+    required_value: Option<u8>,
+    locale: Option<&str>,
+) -> Value {
+    let mut result_description = json!({
+        "id": format!("{}#result-score", achievement_id),
+        "type": ["ResultDescription"],
+        "name": translate(locale, "result_score", &[]),
+        "resultType": PERCENT_RESULT_TYPE,
+        "valueMin": "0",
+        "valueMax": "100",
+    });
+    if let Some(required_value) = required_value {
+        result_description["requiredValue"] = json!(required_value.to_string());
+    }
+    result_description
+}
+
+/// Result description of the score of a single challenge of an achievement.
+pub fn challenge_result_description(achievement_id: &str, challenge_id: &str, name: &str) -> Value {
+    json!({
+        "id": format!("{}#result-{}", achievement_id, slugify(challenge_id)),
+        "type": ["ResultDescription"],
+        "name": name,
+        "resultType": PERCENT_RESULT_TYPE,
+        "valueMin": "0",
+        "valueMax": "100",
+    })
+}
+
+impl AchievementDefinition {
+    fn from_game_path(game_path: &GamePath, metadata: &HashMap<String, GamePathMetadata>) -> Self {
+        let metadata = metadata.get(&game_path.id).cloned().unwrap_or_default();
+        Self {
+            slug: slugify(&game_path.id),
+            game_path_id: game_path.id.clone(),
+            name: game_path.name.clone(),
+            level: metadata.level,
+            tags: metadata.tags,
+            challenges: game_path
+                .challenges
+                .iter()
+                .map(|challenge| ChallengeSummary {
+                    id: challenge.id.clone(),
+                    name: challenge.name.clone(),
+                    description: challenge.description.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        achievement_url(&self.slug)
+    }
+
+    /// URL of the edition of the achievement in one of the catalog languages.
+    pub fn language_edition_url(&self, language: &str) -> String {
+        format!("{}/{}", self.url(), language)
+    }
+
+    /// Editions of the achievement in the other catalog languages, the OB3 form of multi-language achievements.
+    pub fn related(&self, locale: Option<&str>) -> Vec<Value> {
+        let language = resolve_language(locale);
+        languages()
+            .into_iter()
+            .filter(|related| *related != language)
+            .map(|related| {
+                json!({
+                    "id": self.language_edition_url(related),
+                    "type": ["Related"],
+                    "inLanguage": related,
+                })
+            })
+            .collect()
+    }
+
+    /// Criteria of the achievement: completing the challenges of the game path.
+    pub fn criteria_narrative(&self, locale: Option<&str>) -> String {
+        let challenges: Vec<String> = self
+            .challenges
+            .iter()
+            .map(|challenge| format!("- **{}**: {}", challenge.name, challenge.description))
+            .collect();
+        format!(
+            "{}\n\n{}",
+            translate(locale, "criteria_challenges", &[("course", &self.name)]),
+            challenges.join("\n")
+        )
+    }
+
+    /// Alignment of the achievement to its CEFR level.
+    pub fn alignment(&self) -> Vec<Value> {
+        self.level
+            .iter()
+            .map(|level| {
+                json!({
+                    "type": ["Alignment"],
+                    "targetName": format!("CEFR {}", level),
+                    "targetCode": level,
+                    "targetFramework": CEFR_FRAMEWORK,
+                    "targetType": "ext:CEFRLevel",
+                    "targetUrl": CEFR_URL,
+                })
+            })
+            .collect()
+    }
+
+    /// Adds the properties konnektoren-core knows about to the achievement of a credential.
+    pub fn apply(&self, achievement: &mut Value, locale: Option<&str>) {
+        achievement["id"] = json!(self.url());
+        achievement["achievementType"] = json!("Certificate");
+        achievement["criteria"] = json!({
+            "id": format!("{}#criteria", self.url()),
+            "narrative": self.criteria_narrative(locale),
+        });
+        if !self.tags.is_empty() {
+            achievement["tag"] = json!(self.tags);
+        }
+        let alignment = self.alignment();
+        if !alignment.is_empty() {
+            achievement["alignment"] = json!(alignment);
+        }
+        achievement["related"] = json!(self.related(locale));
+    }
+
+    /// Name of a challenge of the game path.
+    pub fn challenge_name(&self, challenge_id: &str) -> Option<&str> {
+        self.challenges
+            .iter()
+            .find(|challenge| challenge.id == challenge_id)
+            .map(|challenge| challenge.name.as_str())
+    }
+
+    /// Result descriptions of the overall score and of each challenge.
+    pub fn result_descriptions(&self, locale: Option<&str>) -> Vec<Value> {
+        let url = self.url();
+        std::iter::once(score_result_description(&url, None, locale))
+            .chain(self.challenges.iter().map(|challenge| {
+                challenge_result_description(&url, &challenge.id, &challenge.name)
+            }))
+            .collect()
+    }
+
+    /// The achievement as JSON-LD document, as published at its URL.
+    pub fn to_json_ld(&self, locale: Option<&str>) -> Value {
+        let mut achievement = json!({
+            "@context": [OB3_CONTEXT],
+            "type": ["Achievement"],
+            "name": self.name,
+            "description": translate(
+                locale,
+                "achievement_definition_description",
+                &[("course", &self.name)],
+            ),
+            "creator": {
+                "id": issuer_profile_url(),
+                "type": ["Profile"],
+            },
+            "inLanguage": resolve_language(locale),
+            "resultDescription": self.result_descriptions(locale),
+        });
+        self.apply(&mut achievement, locale);
+        achievement
+    }
+}
+
+pub fn achievement_definitions() -> &'static [AchievementDefinition] {
+    &ACHIEVEMENTS
+}
+
+/// Finds the achievement of a certificate by its game path id, or its name for older certificates.
+pub fn find_achievement(
+    game_path_id: Option<&str>,
+    game_path_name: &str,
+) -> Option<&'static AchievementDefinition> {
+    ACHIEVEMENTS.iter().find(|achievement| match game_path_id {
+        Some(game_path_id) => achievement.game_path_id == game_path_id,
+        None => achievement.name == game_path_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_achievements_from_game_paths() {
+        crate::config::set_test_env();
+        assert!(!achievement_definitions().is_empty());
+        for achievement in achievement_definitions() {
+            assert!(!achievement.slug.is_empty());
+            assert!(!achievement.challenges.is_empty());
+            assert!(achievement
+                .url()
+                .ends_with(&format!("/ob3/achievements/{}", achievement.slug)));
+        }
+        assert_eq!(slugify("Konnektoren: Level A1"), "konnektoren-level-a1");
+
+        let achievement = &achievement_definitions()[0];
+        let document = achievement.to_json_ld(Some("uk-UA"));
+        assert_eq!(document["id"], json!(achievement.url()));
+        assert_eq!(document["inLanguage"], json!("uk"));
+        assert_eq!(document["creator"]["id"], json!(issuer_profile_url()));
+        assert_eq!(
+            document["related"],
+            json!([
+                {
+                    "id": achievement.language_edition_url("de"),
+                    "type": ["Related"],
+                    "inLanguage": "de",
+                },
+                {
+                    "id": achievement.language_edition_url("en"),
+                    "type": ["Related"],
+                    "inLanguage": "en",
+                },
+            ])
+        );
+    }
+}
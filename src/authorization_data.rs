@@ -0,0 +1,179 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Lifetime of an authorization code in minutes.
+pub const AUTHORIZATION_CODE_TTL_MINUTES: i64 = 10;
+
+/// Time in minutes the learner has to sign in after the wallet's authorization request.
+pub const AUTHORIZATION_SESSION_TTL_MINUTES: i64 = 10;
+
+/// Query parameters of a wallet's authorization request.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuthorizationRequestQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub issuer_state: Option<String>,
+    pub scope: Option<String>,
+    pub authorization_details: Option<String>,
+}
+
+/// An authorization request waiting for the learner to sign in.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuthorizationSession {
+    pub id: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub state: Option<String>,
+    pub code_challenge: String,
+    pub issuer_state: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Nonce and PKCE verifier of a pending login at the OpenID Connect provider.
+    #[serde(default)]
+    pub federated_login: Option<FederatedLogin>,
+}
+
+impl AuthorizationSession {
+    pub fn is_expired(&self) -> bool {
+        self.created_at + Duration::minutes(AUTHORIZATION_SESSION_TTL_MINUTES) <= Utc::now()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FederatedLogin {
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+/// Query parameters of the OpenID Connect provider's redirect back to the issuer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FederatedCallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoginForm {
+    pub session_id: String,
+    pub profile_id: Option<String>,
+    pub password: Option<String>,
+    pub consent: Option<String>,
+}
+
+/// An authorization code bound to the learner's certificates.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuthorizationCodeGrant {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: String,
+    pub profile_id: String,
+    pub certificate_ids: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Authorization details of type `openid_credential` in the token response, each credential
+/// identifier stands for one certificate the wallet can request at the credential endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialAuthorizationDetails {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub credential_configuration_id: String,
+    pub credential_identifiers: Vec<String>,
+}
+
+impl CredentialAuthorizationDetails {
+    pub fn new(credential_configuration_id: String, credential_identifiers: Vec<String>) -> Self {
+        Self {
+            type_: "openid_credential".to_string(),
+            credential_configuration_id,
+            credential_identifiers,
+        }
+    }
+
+    /// Lists one credential identifier per certificate, grouped by the credential configuration
+    /// each certificate is issued as.
+    pub fn for_certificates(
+        certificate_ids: Vec<String>,
+        credential_configuration_id: impl Fn(&str) -> &'static str,
+    ) -> Vec<Self> {
+        let mut authorization_details: Vec<Self> = vec![];
+        for certificate_id in certificate_ids {
+            let configuration_id = credential_configuration_id(&certificate_id);
+            match authorization_details
+                .iter_mut()
+                .find(|details| details.credential_configuration_id == configuration_id)
+            {
+                Some(details) => details.credential_identifiers.push(certificate_id),
+                None => authorization_details.push(Self::new(
+                    configuration_id.to_string(),
+                    vec![certificate_id],
+                )),
+            }
+        }
+        authorization_details
+    }
+}
+
+/// Derives the `S256` code challenge for a PKCE code verifier.
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Verifies a PKCE `code_verifier` against an `S256` code challenge (RFC 7636).
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    let computed = pkce_challenge(code_verifier);
+    bool::from(computed.as_bytes().ct_eq(code_challenge.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_pkce() {
+        // Example from RFC 7636, Appendix B
+        let code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let code_challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+        assert!(verify_pkce(code_verifier, code_challenge));
+        assert!(!verify_pkce("wrong-verifier", code_challenge));
+    }
+
+    #[test]
+    fn test_authorization_details_for_certificates() {
+        let certificate_ids = vec!["cert-1", "clr-1", "cert-2"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let authorization_details =
+            CredentialAuthorizationDetails::for_certificates(certificate_ids, |id| {
+                match id.starts_with("clr") {
+                    true => "ClrCredential",
+                    false => "KonnektorenCertificate",
+                }
+            });
+
+        assert_eq!(authorization_details.len(), 2);
+        assert_eq!(authorization_details[0].type_, "openid_credential");
+        assert_eq!(
+            authorization_details[0].credential_configuration_id,
+            "KonnektorenCertificate"
+        );
+        assert_eq!(
+            authorization_details[0].credential_identifiers,
+            vec!["cert-1", "cert-2"]
+        );
+        assert_eq!(
+            authorization_details[1].credential_identifiers,
+            vec!["clr-1"]
+        );
+    }
+}
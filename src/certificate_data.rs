@@ -1,40 +1,212 @@
+use crate::achievements::{
+    achievement_url, challenge_result_description, find_achievement, score_result_description,
+    slugify, AchievementDefinition, OB3_CONTEXT,
+};
+use crate::i18n::{resolve_language, translate};
+use crate::issuer_profile::{issuer_other_identifiers, issuer_profile_url, ISSUER_PROFILE_NAME};
+use crate::public_verification::verification_url;
 use chrono::Utc;
+use serde_json::{json, Value};
 use types_ob_v3::prelude::{
     Achievement, AchievementBuilder, AchievementCredential, AchievementCredentialBuilder,
     AchievementCredentialType, AchievementSubject, AchievementSubjectBuilder, Criteria,
     CriteriaBuilder, ImageBuilder, Profile, ProfileBuilder, ResultBuilder,
 };
+use url::Url;
+use uuid::Uuid;
+
+/// Credential configuration of a single certificate.
+pub const CERTIFICATE_CREDENTIAL_CONFIGURATION_ID: &str = "KonnektorenCertificate";
+
+/// Score of a single challenge of the game path.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ChallengeResult {
+    pub challenge_id: String,
+    /// Name of the challenge, taken from konnektoren-core if not given.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub performance_percentage: u8,
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct CertificateData {
     pub game_path_name: String,
+    /// Identifier of the konnektoren-core game path the certificate was earned on.
+    #[serde(default)]
+    pub game_path_id: Option<String>,
     pub total_challenges: usize,
     pub solved_challenges: usize,
     pub performance_percentage: u8,
     pub profile_name: String,
+    /// Identifier of the learner's Konnektoren profile, used to find their certificates.
+    #[serde(default)]
+    pub profile_id: Option<String>,
     pub date: chrono::DateTime<Utc>,
+    /// Certificates that need a teacher's approval are issued via the deferred credential endpoint.
+    #[serde(default)]
+    pub requires_review: bool,
+    /// Preferred locale of the learner, e.g. `de-DE`, the credential texts are written in its language.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Performance in percent needed to pass the game path.
+    #[serde(default)]
+    pub pass_threshold: Option<u8>,
+    #[serde(default)]
+    pub challenge_results: Vec<ChallengeResult>,
+    /// Page on which the learner can review their results, referenced as evidence.
+    #[serde(default)]
+    pub summary_url: Option<String>,
+}
+
+impl CertificateData {
+    /// The achievement of the certificate's game path, if it is known to konnektoren-core.
+    pub fn achievement(&self) -> Option<&'static AchievementDefinition> {
+        find_achievement(self.game_path_id.as_deref(), &self.game_path_name)
+    }
+
+    /// Stable URL of the certificate's achievement.
+    pub fn achievement_id(&self) -> String {
+        match self.achievement() {
+            Some(achievement) => achievement.url(),
+            None => achievement_url(&slugify(
+                self.game_path_id.as_deref().unwrap_or(&self.game_path_name),
+            )),
+        }
+    }
+
+    fn challenge_name(&self, challenge_result: &ChallengeResult) -> String {
+        challenge_result
+            .name
+            .clone()
+            .or_else(|| {
+                self.achievement()
+                    .and_then(|achievement| {
+                        achievement.challenge_name(&challenge_result.challenge_id)
+                    })
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| challenge_result.challenge_id.clone())
+    }
+
+    /// Result descriptions of the overall score and of each challenge with a result.
+    pub fn result_descriptions(&self) -> Vec<Value> {
+        let achievement_id = self.achievement_id();
+        std::iter::once(score_result_description(
+            &achievement_id,
+            self.pass_threshold,
+            self.locale.as_deref(),
+        ))
+        .chain(self.challenge_results.iter().map(|challenge_result| {
+            challenge_result_description(
+                &achievement_id,
+                &challenge_result.challenge_id,
+                &self.challenge_name(challenge_result),
+            )
+        }))
+        .collect()
+    }
+
+    /// Results of the learner, each referencing its result description.
+    pub fn results(&self) -> Vec<Value> {
+        let result_descriptions = self.result_descriptions();
+        let mut score = json!({
+            "type": ["Result"],
+            "resultDescription": result_descriptions[0]["id"],
+            "value": self.performance_percentage.to_string(),
+        });
+        if let Some(pass_threshold) = self.pass_threshold {
+            score["status"] = match self.performance_percentage >= pass_threshold {
+                true => json!("Completed"),
+                false => json!("Failed"),
+            };
+        }
+        std::iter::once(score)
+            .chain(
+                self.challenge_results
+                    .iter()
+                    .zip(&result_descriptions[1..])
+                    .map(|(challenge_result, result_description)| {
+                        json!({
+                            "type": ["Result"],
+                            "resultDescription": result_description["id"],
+                            "value": challenge_result.performance_percentage.to_string(),
+                        })
+                    }),
+            )
+            .collect()
+    }
+
+    /// Evidence of the certificate, the learner's summary page if there is one.
+    pub fn evidence(&self) -> Vec<Value> {
+        self.summary_url
+            .iter()
+            .map(|summary_url| {
+                json!({
+                    "id": summary_url,
+                    "type": ["Evidence"],
+                    "name": translate(self.locale.as_deref(), "evidence_summary", &[]),
+                    "genre": "Summary",
+                })
+            })
+            .collect()
+    }
+
+    /// Builds the achievement credential of the certificate for the holder, ready to be signed.
+    pub fn to_verifiable_credential(&self, subject_did: &Url) -> Value {
+        let achievement_credential: AchievementCredential = self.clone().into();
+        let mut verifiable_credential = serde_json::to_value(achievement_credential).unwrap();
+        verifiable_credential["id"] = json!(verification_url(&Uuid::new_v4().to_string()));
+        verifiable_credential["issuer"]["otherIdentifier"] = issuer_other_identifiers();
+        verifiable_credential["credentialSubject"]["id"] = json!(subject_did);
+        verifiable_credential["credentialSubject"]["achievement"]["inLanguage"] =
+            json!(resolve_language(self.locale.as_deref()));
+        if let Some(achievement) = self.achievement() {
+            achievement.apply(
+                &mut verifiable_credential["credentialSubject"]["achievement"],
+                self.locale.as_deref(),
+            );
+        }
+        self.apply_results(&mut verifiable_credential);
+        verifiable_credential
+    }
+
+    /// Adds result descriptions, results and evidence to the credential.
+    pub fn apply_results(&self, credential: &mut Value) {
+        credential["credentialSubject"]["achievement"]["resultDescription"] =
+            json!(self.result_descriptions());
+        credential["credentialSubject"]["result"] = json!(self.results());
+        let evidence = self.evidence();
+        if !evidence.is_empty() {
+            credential["evidence"] = json!(evidence);
+        }
+    }
 }
 
 impl From<CertificateData> for AchievementCredential {
     fn from(cert_data: CertificateData) -> Self {
+        let locale = cert_data.locale.as_deref();
         let criteria: Criteria = CriteriaBuilder::default()
-            .narrative(format!(
-                "Completed {} out of {} challenges with a performance of {}%",
-                cert_data.solved_challenges,
-                cert_data.total_challenges,
-                cert_data.performance_percentage
+            .narrative(translate(
+                locale,
+                "criteria_narrative",
+                &[
+                    ("solved", &cert_data.solved_challenges.to_string()),
+                    ("total", &cert_data.total_challenges.to_string()),
+                    ("performance", &cert_data.performance_percentage.to_string()),
+                ],
             ))
             .try_into()
             .unwrap();
 
         let achievement: Achievement = AchievementBuilder::default()
-            .id("https://example.com/achievements/certificate".to_string())
+            .id(cert_data.achievement_id())
             .type_("Achievement")
             .criteria(criteria)
             .name(cert_data.game_path_name.clone())
-            .description(format!(
-                "This certificate recognizes the achievement of {} in completing the course.",
-                cert_data.profile_name
+            .description(translate(
+                locale,
+                "achievement_description",
+                &[("name", &cert_data.profile_name)],
             ))
             .image(
                 ImageBuilder::default()
@@ -58,17 +230,16 @@ impl From<CertificateData> for AchievementCredential {
             )
             .credits_earned(cert_data.solved_challenges as f64)
             .result(vec![ResultBuilder::default()
-                .achieved_level(cert_data.game_path_name.clone())
                 .type_("Result")
-                .value(Some(format!("{}%", cert_data.performance_percentage)))])
+                .value(Some(cert_data.performance_percentage.to_string()))])
             .try_into()
             .unwrap();
 
         // Building issuer profile
         let issuer: Profile = ProfileBuilder::default()
-            .id("vc.konnektoren.help")
+            .id(issuer_profile_url())
             .type_("Profile")
-            .name("Konnektoren".to_string())
+            .name(ISSUER_PROFILE_NAME.to_string())
             .image(
                 ImageBuilder::default()
                     .id(konnektoren_image())
@@ -80,10 +251,7 @@ impl From<CertificateData> for AchievementCredential {
 
         // Building the achievement credential
         AchievementCredentialBuilder::default()
-            .context(vec![
-                "https://www.w3.org/2018/credentials/v1",
-                "https://purl.imsglobal.org/spec/ob/v3p0/context-3.0.2.json",
-            ])
+            .context(vec!["https://www.w3.org/2018/credentials/v1", OB3_CONTEXT])
             .credential_subject(&achievement_subject)
             .id("http://example.com/credentials/3527".to_string())
             .name(cert_data.game_path_name.clone())
@@ -94,9 +262,13 @@ impl From<CertificateData> for AchievementCredential {
             .issuance_date(cert_data.date.to_rfc3339())
             .issuer(issuer)
             .awarded_date(cert_data.date.to_rfc3339())
-            .description(format!(
-                "This certificate recognizes the achievement of {} in completing the {} course.",
-                cert_data.profile_name, cert_data.game_path_name
+            .description(translate(
+                locale,
+                "credential_description",
+                &[
+                    ("name", &cert_data.profile_name),
+                    ("course", &cert_data.game_path_name),
+                ],
             ))
             .image(
                 ImageBuilder::default()
@@ -119,46 +291,98 @@ fn konnektoren_image() -> String {
 mod tests {
     use super::*;
     use chrono::{NaiveDateTime, TimeZone, Utc};
-    use std::fs::File;
-    use std::io::BufReader;
     use types_ob_v3::prelude::AchievementCredential;
 
     #[test]
     fn test_conversion() {
+        crate::config::set_test_env();
         let naive_date = NaiveDateTime::parse_from_str("2024-09-30 08:00:00", "%Y-%m-%d %H:%M:%S")
             .expect("Failed to parse date");
         let utc_date = Utc.from_utc_datetime(&naive_date);
 
         let cert_data = CertificateData {
             game_path_name: "Introduction to Rust".to_string(),
+            game_path_id: None,
             total_challenges: 10,
             solved_challenges: 8,
             performance_percentage: 80,
             profile_name: "Alice Rustacean".to_string(),
+            profile_id: None,
             date: utc_date, // Use Utc DateTime here
+            requires_review: false,
+            locale: None,
+            pass_threshold: None,
+            challenge_results: vec![],
+            summary_url: None,
         };
 
-        // Convert CertificateData into AchievementCredential
-        let achievement_credential: AchievementCredential = cert_data.into();
+        let credential = serde_json::to_value(AchievementCredential::from(cert_data)).unwrap();
 
-        // Write achievement_credential to file
-        let file =
-            File::create("assets/konnektoren_certificate.json").expect("Failed to create file");
-        serde_json::to_writer_pretty(file, &achievement_credential)
-            .expect("Couldn't write to file");
+        assert_eq!(
+            credential["type"],
+            json!(["VerifiableCredential", "OpenBadgeCredential"])
+        );
+        assert_eq!(credential["issuer"]["id"], json!(issuer_profile_url()));
+        assert_eq!(
+            credential["credentialSubject"]["achievement"]["id"],
+            json!("http://localhost:3000/ob3/achievements/introduction-to-rust")
+        );
+        assert_eq!(credential["credentialSubject"]["creditsEarned"], json!(8.0));
+        assert_eq!(credential["issuanceDate"], json!("2024-09-30T08:00:00Z"));
+    }
 
-        // Load JSON from file to test the conversion
-        let file = File::open("assets/konnektoren_certificate.json")
-            .expect("Failed to open file for reading");
-        let reader = BufReader::new(file);
+    #[test]
+    fn test_results() {
+        crate::config::set_test_env();
+        let cert_data = CertificateData {
+            game_path_name: "Introduction to Rust".to_string(),
+            game_path_id: None,
+            total_challenges: 2,
+            solved_challenges: 2,
+            performance_percentage: 75,
+            profile_name: "Alice Rustacean".to_string(),
+            profile_id: None,
+            date: Utc::now(),
+            requires_review: false,
+            locale: None,
+            pass_threshold: Some(60),
+            challenge_results: vec![
+                ChallengeResult {
+                    challenge_id: "ownership".to_string(),
+                    name: Some("Ownership".to_string()),
+                    performance_percentage: 90,
+                },
+                ChallengeResult {
+                    challenge_id: "lifetimes".to_string(),
+                    name: None,
+                    performance_percentage: 60,
+                },
+            ],
+            summary_url: Some("https://konnektoren.help/results/42".to_string()),
+        };
 
-        let json_value_from_file: serde_json::Value =
-            serde_json::from_reader(reader).expect("Couldn't read from file");
+        let mut credential =
+            serde_json::to_value(AchievementCredential::from(cert_data.clone())).unwrap();
+        cert_data.apply_results(&mut credential);
+
+        let result_descriptions =
+            credential["credentialSubject"]["achievement"]["resultDescription"].clone();
+        assert_eq!(result_descriptions[0]["requiredValue"], json!("60"));
+        assert_eq!(result_descriptions[0]["valueMax"], json!("100"));
+        assert_eq!(result_descriptions[2]["name"], json!("lifetimes"));
+
+        let results = credential["credentialSubject"]["result"].clone();
+        assert_eq!(results.as_array().unwrap().len(), 3);
+        assert_eq!(results[0]["status"], json!("Completed"));
+        assert_eq!(results[1]["value"], json!("90"));
+        assert_eq!(
+            results[1]["resultDescription"],
+            result_descriptions[1]["id"]
+        );
 
-        // Ensure the generated credential matches the expected JSON structure
         assert_eq!(
-            serde_json::to_value(&achievement_credential).unwrap(),
-            json_value_from_file
+            credential["evidence"][0]["id"],
+            json!("https://konnektoren.help/results/42")
         );
     }
 }
@@ -0,0 +1,55 @@
+use super::persistent_storage;
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+pub fn list_certificates(profile_id: Option<&str>) -> Result<bool> {
+    let storage = persistent_storage()?;
+    let certificates = storage.list_certificates();
+    let certificates = certificates.iter().filter(|(_, certificate)| {
+        profile_id.map_or(true, |profile_id| {
+            certificate.profile_id.as_deref() == Some(profile_id)
+        })
+    });
+    for (certificate_id, certificate) in certificates {
+        println!(
+            "{}  {}  {:<24}  {:<32}  {:>3}%{}",
+            certificate_id,
+            certificate.date.format("%Y-%m-%d"),
+            certificate.profile_name,
+            certificate.game_path_name,
+            certificate.performance_percentage,
+            if storage.is_revoked(certificate_id) {
+                "  revoked"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(true)
+}
+
+pub fn show_certificate(certificate_id: &str) -> Result<bool> {
+    let storage = persistent_storage()?;
+    let certificate = storage
+        .get_certificate(certificate_id)
+        .ok_or_else(|| anyhow!("Certificate {} not found", certificate_id))?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "certificate_id": certificate_id,
+            "certificate": certificate,
+            "revoked_at": storage.get_revocation_date(certificate_id),
+        }))?
+    );
+    Ok(true)
+}
+
+/// Revokes a certificate, the running server picks the revocation up from the storage file.
+pub fn revoke_certificate(certificate_id: &str) -> Result<bool> {
+    let storage = persistent_storage()?;
+    if !storage.revoke_certificate(certificate_id) {
+        return Err(anyhow!("Certificate {} not found", certificate_id));
+    }
+    println!("Revoked certificate {}", certificate_id);
+    Ok(true)
+}
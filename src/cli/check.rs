@@ -0,0 +1,162 @@
+use crate::clients::load_clients;
+use crate::config::load_oidc_config;
+use crate::credential_schema::{has_credential_schema, CREDENTIAL_CONFIGURATION_FILES};
+use crate::display::load_display_config;
+use crate::learners::load_learners;
+use crate::partners::load_partners;
+use crate::storage::StorageSnapshot;
+use crate::webhooks::load_webhook_endpoints;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fs::File;
+use std::path::Path;
+use url::Url;
+
+/// Secret of `example.env`, which must never sign real credentials.
+const EXAMPLE_PRIVATE_KEY: &str = "this-is-a-very-UNSAFE-issuer-secret-key";
+
+enum Outcome {
+    Ok(String),
+    Warning(String),
+    Error(String),
+}
+
+fn check_private_key() -> Outcome {
+    match env::var("PRIVATE_KEY") {
+        Ok(key) if key == EXAMPLE_PRIVATE_KEY => {
+            Outcome::Warning("the example key is used, generate one with `key generate`".into())
+        }
+        Ok(key) if key.is_empty() => Outcome::Error("is empty".into()),
+        Ok(_) => Outcome::Ok("set".into()),
+        Err(_) => Outcome::Error("not set".into()),
+    }
+}
+
+fn check_issuer_url() -> Outcome {
+    match env::var("ISSUER_URL").map(|url| Url::parse(&url)) {
+        Ok(Ok(url)) => Outcome::Ok(url.to_string()),
+        Ok(Err(e)) => Outcome::Error(format!("invalid URL: {}", e)),
+        Err(_) => Outcome::Error("not set".into()),
+    }
+}
+
+fn check_admin_token() -> Outcome {
+    match env::var("ADMIN_TOKEN").as_deref() {
+        Ok("") | Err(_) => Outcome::Warning("not set, the admin API is disabled".into()),
+        Ok("change-me") => Outcome::Warning("the example token is used".into()),
+        Ok(_) => Outcome::Ok("set".into()),
+    }
+}
+
+fn check_oidc() -> Outcome {
+    match load_oidc_config() {
+        Ok(Some(config)) => Outcome::Ok(config.discovery_url),
+        Ok(None) => Outcome::Ok("not set, federated login is disabled".into()),
+        Err(e) => Outcome::Error(e.to_string()),
+    }
+}
+
+fn check_access_token_format() -> Outcome {
+    match env::var("ACCESS_TOKEN_FORMAT").as_deref() {
+        Err(_) => Outcome::Ok("opaque".into()),
+        Ok(format @ ("opaque" | "jwt")) => Outcome::Ok(format.into()),
+        Ok(format) => Outcome::Warning(format!("unknown format {}, using opaque", format)),
+    }
+}
+
+/// Outcome of loading an optional file referenced by an environment variable.
+fn check_file(variable: &str, count: impl FnOnce() -> Result<usize>) -> Outcome {
+    let Ok(path) = env::var(variable) else {
+        return Outcome::Ok("not set".into());
+    };
+    if !Path::new(&path).exists() {
+        return Outcome::Warning(format!("{} does not exist", path));
+    }
+    match count() {
+        Ok(count) => Outcome::Ok(format!("{} entries", count)),
+        Err(e) => Outcome::Error(e.to_string()),
+    }
+}
+
+fn check_storage_file() -> Outcome {
+    let Ok(path) = env::var("STORAGE_FILE") else {
+        return Outcome::Warning("not set, certificates are lost on restart".into());
+    };
+    match StorageSnapshot::load(Path::new(&path)) {
+        Ok(_) => Outcome::Ok(path),
+        Err(e) => Outcome::Error(e.to_string()),
+    }
+}
+
+fn check_credential_configurations() -> Outcome {
+    let result: Result<()> = CREDENTIAL_CONFIGURATION_FILES.iter().try_for_each(
+        |(credential_configuration_id, path)| {
+            serde_json::from_reader::<_, serde_json::Value>(File::open(path)?)
+                .map_err(|e| anyhow!("{}: {}", path, e))?;
+            match has_credential_schema(credential_configuration_id) {
+                true => Ok(()),
+                false => Err(anyhow!("{}: no valid credential schema", path)),
+            }
+        },
+    );
+    match result {
+        Ok(()) => Outcome::Ok(format!(
+            "{} configurations",
+            CREDENTIAL_CONFIGURATION_FILES.len()
+        )),
+        Err(e) => Outcome::Error(e.to_string()),
+    }
+}
+
+/// Loads every setting and configured file, prints the outcome of each and returns whether
+/// none of them has an error.
+pub fn check_config() -> bool {
+    let checks = [
+        ("PRIVATE_KEY", check_private_key()),
+        ("ISSUER_URL", check_issuer_url()),
+        ("ADMIN_TOKEN", check_admin_token()),
+        ("ACCESS_TOKEN_FORMAT", check_access_token_format()),
+        ("OIDC_DISCOVERY_URL", check_oidc()),
+        (
+            "DISPLAY_FILE",
+            match load_display_config() {
+                Ok(config) => Outcome::Ok(format!("{} issuer locales", config.issuer.len())),
+                Err(e) => Outcome::Error(e.to_string()),
+            },
+        ),
+        (
+            "WEBHOOKS_FILE",
+            check_file("WEBHOOKS_FILE", || Ok(load_webhook_endpoints()?.len())),
+        ),
+        (
+            "LEARNERS_FILE",
+            check_file("LEARNERS_FILE", || Ok(load_learners()?.len())),
+        ),
+        (
+            "CLIENTS_FILE",
+            check_file("CLIENTS_FILE", || Ok(load_clients()?.len())),
+        ),
+        (
+            "PARTNERS_FILE",
+            check_file("PARTNERS_FILE", || Ok(load_partners()?.len())),
+        ),
+        ("STORAGE_FILE", check_storage_file()),
+        (
+            "credential configurations",
+            check_credential_configurations(),
+        ),
+    ];
+
+    let mut valid = true;
+    for (name, outcome) in checks {
+        match outcome {
+            Outcome::Ok(message) => println!("✔ {}: {}", name, message),
+            Outcome::Warning(message) => println!("! {}: {}", name, message),
+            Outcome::Error(message) => {
+                valid = false;
+                println!("✘ {}: {}", name, message)
+            }
+        }
+    }
+    valid
+}
@@ -0,0 +1,33 @@
+use crate::config::try_load_private_key;
+use crate::issuer_key::{ed25519_key_from_secret, key_id};
+use anyhow::Result;
+use did_key::DIDCore;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+/// Prints the issuer's DID and key id, or its DID document.
+pub fn show_did(document: bool) -> Result<bool> {
+    let private_key = try_load_private_key()?;
+    let key = ed25519_key_from_secret(&private_key);
+    let did_document = key.get_did_document(did_key::Config::default());
+    if document {
+        println!("{}", serde_json::to_string_pretty(&did_document)?);
+    } else {
+        println!("DID:    {}", did_document.id);
+        println!("Key id: {}", key_id(&key));
+    }
+    Ok(true)
+}
+
+/// Prints a new random `PRIVATE_KEY` and the DID the issuer would have with it.
+pub fn generate_key() -> Result<bool> {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    let private_key = hex::encode(secret);
+    let did = ed25519_key_from_secret(&private_key)
+        .get_did_document(did_key::Config::default())
+        .id;
+    println!("PRIVATE_KEY={}", private_key);
+    println!("DID:    {}", did);
+    Ok(true)
+}
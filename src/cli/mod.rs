@@ -0,0 +1,130 @@
+mod certificates;
+mod check;
+mod keys;
+mod offer;
+mod verify;
+
+use crate::config::load_storage_file;
+use crate::storage::MemoryStorage;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use certificates::{list_certificates, revoke_certificate, show_certificate};
+use check::check_config;
+use keys::{generate_key, show_did};
+use offer::create_offer;
+
+pub use verify::{describe_report, read_credential, run_verify};
+
+/// Issues Konnektoren certificates as verifiable credentials.
+#[derive(Parser, Debug)]
+#[command(name = "konnektoren-vc", version, about)]
+pub struct Cli {
+    /// Starts the server if no command is given.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Starts the server.
+    Serve,
+    /// Verifies a JWT VC without network access, exits non-zero if it is not valid.
+    Verify {
+        /// The JWT VC or a file containing it, `-` reads it from stdin.
+        credential: String,
+        /// Prints the verification report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Creates credential offers.
+    #[command(subcommand)]
+    Offer(OfferCommand),
+    /// Shows the issuer's DID.
+    #[command(subcommand)]
+    Did(DidCommand),
+    /// Manages issuer keys.
+    #[command(subcommand)]
+    Key(KeyCommand),
+    /// Inspects and revokes stored certificates.
+    #[command(subcommand)]
+    Certificates(CertificatesCommand),
+    /// Checks the configuration.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OfferCommand {
+    /// Creates a pre-authorized offer and prints its URL and QR code.
+    Create {
+        /// JSON file with a certificate, or a list of certificates for a batch offer.
+        #[arg(long)]
+        from: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DidCommand {
+    /// Prints the DID derived from `PRIVATE_KEY`.
+    Show {
+        /// Prints the whole DID document.
+        #[arg(long)]
+        document: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeyCommand {
+    /// Generates a random `PRIVATE_KEY` and prints the DID it derives.
+    Generate,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CertificatesCommand {
+    /// Lists the stored certificates.
+    List {
+        /// Only lists the certificates of this learner profile.
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Prints a stored certificate.
+    Show { certificate_id: String },
+    /// Revokes a certificate.
+    Revoke { certificate_id: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Loads every configured file and reports what is missing or invalid.
+    Check,
+}
+
+/// The storage the server keeps in `STORAGE_FILE`, the command line has no other way to reach it.
+fn persistent_storage() -> Result<MemoryStorage> {
+    let storage_file = load_storage_file()
+        .ok_or_else(|| anyhow!("STORAGE_FILE must be set to manage the issuer's storage"))?;
+    MemoryStorage::new().with_storage_file(storage_file)
+}
+
+/// Runs a command other than `serve`, returns whether it succeeded.
+pub async fn run(command: Command) -> Result<bool> {
+    match command {
+        Command::Serve => Err(anyhow!("The server is not started by run")),
+        Command::Verify { credential, json } => run_verify(&credential, json).await,
+        Command::Offer(OfferCommand::Create { from }) => create_offer(&from).await,
+        Command::Did(DidCommand::Show { document }) => show_did(document),
+        Command::Key(KeyCommand::Generate) => generate_key(),
+        Command::Certificates(CertificatesCommand::List { profile }) => {
+            list_certificates(profile.as_deref())
+        }
+        Command::Certificates(CertificatesCommand::Show { certificate_id }) => {
+            show_certificate(&certificate_id)
+        }
+        Command::Certificates(CertificatesCommand::Revoke { certificate_id }) => {
+            revoke_certificate(&certificate_id)
+        }
+        Command::Config(ConfigCommand::Check) => Ok(check_config()),
+    }
+}
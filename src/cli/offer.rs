@@ -0,0 +1,38 @@
+use super::persistent_storage;
+use crate::certificate_data::CertificateData;
+use crate::server::create_manager;
+use crate::services::{qr_to_string, CertificateService};
+use crate::webhooks::{load_webhook_endpoints, WebhookDispatcher};
+use anyhow::Result;
+use qrcodegen::{QrCode, QrCodeEcc};
+use serde_json::Value;
+use std::fs::File;
+use std::net::TcpListener;
+use std::path::Path;
+
+/// Stores the certificates of a JSON file and prints the offer URL and its QR code, the
+/// `offer.created` webhook is sent like by the server.
+pub async fn create_offer(path: &Path) -> Result<bool> {
+    let certificates: Vec<CertificateData> =
+        match serde_json::from_reader::<_, Value>(File::open(path)?)? {
+            Value::Array(certificates) => certificates
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()?,
+            certificate => vec![serde_json::from_value(certificate)?],
+        };
+
+    // The manager only needs a listener to know its address, the offer uses `ISSUER_URL`
+    let storage =
+        persistent_storage()?.with_webhooks(WebhookDispatcher::new(load_webhook_endpoints()?));
+    let manager = create_manager(TcpListener::bind("127.0.0.1:0")?, storage)?;
+    let offer_url = CertificateService::new(&manager).generate_batch_offer_url(&certificates)?;
+
+    let qr = QrCode::encode_text(&offer_url, QrCodeEcc::Medium)?;
+    println!("{}", qr_to_string(&qr));
+    println!("{}", offer_url);
+    if let Some(webhooks) = manager.storage.webhooks() {
+        webhooks.finish().await;
+    }
+    Ok(true)
+}
@@ -0,0 +1,138 @@
+use crate::presentation_data::performance_of;
+use crate::verification::{
+    CheckStatus, CredentialVerifier, VerificationReport, VerificationRequest,
+};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+
+/// Reads a JWT VC from stdin (`-`), a file, or takes the argument itself. Files may also
+/// hold a credential response with the JWT in `credential`.
+pub fn read_credential(input: &str) -> Result<String> {
+    let content = if input == "-" {
+        let mut content = String::new();
+        std::io::stdin().read_to_string(&mut content)?;
+        content
+    } else if Path::new(input).is_file() {
+        std::fs::read_to_string(input)?
+    } else {
+        input.to_string()
+    };
+    let content = content.trim();
+    if content.starts_with('{') {
+        let response: Value = serde_json::from_str(content)?;
+        return response["credential"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("The JSON has no credential"));
+    }
+    Ok(content.to_string())
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// Achievement and results of an OB3 credential, e.g. `Introduction to Rust (A2): 80%`.
+fn describe_achievement(credential: &Value) -> Vec<String> {
+    let subject = &credential["credentialSubject"];
+    if let Some(nested) = subject["verifiableCredential"].as_array() {
+        return nested.iter().flat_map(describe_achievement).collect();
+    }
+    let achievement = &subject["achievement"];
+    let Some(name) = text(&achievement["name"]) else {
+        return vec![];
+    };
+    let level = achievement["alignment"][0]["targetCode"]
+        .as_str()
+        .map(|level| format!(" ({})", level))
+        .unwrap_or_default();
+    let score = performance_of(credential)
+        .map(|performance| format!(": {}%", performance))
+        .unwrap_or_default();
+    vec![format!("{}{}{}", name, level, score)]
+}
+
+/// Human readable summary of a verification report and its credential.
+pub fn describe_report(report: &VerificationReport) -> String {
+    let credential = report.credential.clone().unwrap_or_default();
+    let issuer_name = text(&credential["issuer"]["name"]);
+    let mut lines = vec![];
+    let mut field = |label: &str, value: Option<String>| {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            lines.push(format!("{:<12} {}", label, value));
+        }
+    };
+    field("Credential", text(&credential["id"]));
+    field("Type", Some(report.credential_types.join(", ")));
+    field(
+        "Issuer",
+        match (issuer_name, &report.issuer) {
+            (Some(name), Some(did)) => Some(format!("{} ({})", name, did)),
+            (name, did) => name.or_else(|| did.clone()),
+        },
+    );
+    field("Subject", report.subject.clone());
+    field("Name", text(&credential["name"]));
+    field("Description", text(&credential["description"]));
+    for achievement in describe_achievement(&credential) {
+        field("Achievement", Some(achievement));
+    }
+    field(
+        "Issued",
+        text(&credential["validFrom"]).or_else(|| text(&credential["issuanceDate"])),
+    );
+    field(
+        "Expires",
+        text(&credential["validUntil"]).or_else(|| text(&credential["expirationDate"])),
+    );
+
+    lines.push(String::new());
+    for check in &report.checks {
+        let symbol = match check.status {
+            CheckStatus::Passed => "✔",
+            CheckStatus::Failed => "✘",
+            CheckStatus::Skipped => "-",
+        };
+        lines.push(match &check.message {
+            Some(message) => format!("{} {}: {}", symbol, check.check, message),
+            None => format!("{} {}", symbol, check.check),
+        });
+    }
+    lines.push(String::new());
+    lines.push(if report.valid { "Valid" } else { "Invalid" }.to_string());
+    lines.join("\n")
+}
+
+/// Verifies a credential offline and prints the report, returns whether it is valid.
+pub async fn run_verify(input: &str, json: bool) -> Result<bool> {
+    let credential = read_credential(input)?;
+    let report = CredentialVerifier::offline()
+        .verify(&VerificationRequest::jwt_vc(&credential))
+        .await;
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&report)?),
+        false => println!("{}", describe_report(&report)),
+    }
+    Ok(report.valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_credential() {
+        assert_eq!(read_credential(" a.b.c\n").unwrap(), "a.b.c");
+        assert_eq!(
+            read_credential(r#"{"credential": "a.b.c"}"#).unwrap(),
+            "a.b.c"
+        );
+        assert!(read_credential(r#"{"format": "jwt_vc_json"}"#).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{env, fs::File};
+
+/// A wallet registered for the authorization code flow.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Client {
+    pub client_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Redirect URIs the wallet may use, compared by exact string match.
+    pub redirect_uris: Vec<String>,
+}
+
+impl Client {
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris
+            .iter()
+            .any(|allowed| allowed == redirect_uri)
+    }
+}
+
+/// Loads the registered wallets from the JSON file referenced by `CLIENTS_FILE`.
+pub fn load_clients() -> Result<Vec<Client>> {
+    match env::var("CLIENTS_FILE") {
+        Ok(path) => Ok(serde_json::from_reader(File::open(path)?)?),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+pub fn find_client(client_id: &str) -> Option<Client> {
+    load_clients()
+        .map_err(|e| log::error!("Failed to load clients: {}", e))
+        .ok()?
+        .into_iter()
+        .find(|client| client.client_id == client_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_redirect_uri() {
+        let client = Client {
+            client_id: "wallet".to_string(),
+            name: None,
+            redirect_uris: vec!["https://wallet.example.com/callback".to_string()],
+        };
+
+        assert!(client.allows_redirect_uri("https://wallet.example.com/callback"));
+        assert!(!client.allows_redirect_uri("https://wallet.example.com/callback/"));
+        assert!(!client.allows_redirect_uri("https://wallet.example.com/callback?next=x"));
+        assert!(!client.allows_redirect_uri("https://attacker.example.com/callback"));
+    }
+}
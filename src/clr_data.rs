@@ -0,0 +1,137 @@
+use crate::achievements::OB3_CONTEXT;
+use crate::certificate_data::CertificateData;
+use crate::i18n::translate;
+use crate::issuer_profile::{issuer_other_identifiers, issuer_profile_url, ISSUER_PROFILE_NAME};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+use uuid::Uuid;
+
+/// Credential configuration of the Comprehensive Learner Record.
+pub const CLR_CREDENTIAL_CONFIGURATION_ID: &str = "KonnektorenClr";
+
+/// JSON-LD context of CLR 2.0 documents.
+pub const CLR_CONTEXT: &str = "https://purl.imsglobal.org/spec/clr/v2p0/context-2.0.1.json";
+
+/// Certificates of a learner that are issued together as one `ClrCredential`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClrBundle {
+    pub profile_id: String,
+    pub certificate_ids: Vec<String>,
+}
+
+/// Builds a CLR 2.0 credential that nests the achievement credentials of all certificates.
+pub fn clr_credential(certificates: &[CertificateData], subject_did: &Url) -> Value {
+    let profile_name = certificates
+        .first()
+        .map(|certificate| certificate.profile_name.clone())
+        .unwrap_or_default();
+    // The most recent certificate tells the learner's current language
+    let locale = certificates
+        .iter()
+        .max_by_key(|certificate| certificate.date)
+        .and_then(|certificate| certificate.locale.clone());
+
+    let verifiable_credentials: Vec<Value> = certificates
+        .iter()
+        .map(|certificate| certificate.to_verifiable_credential(subject_did))
+        .collect();
+    let mut achievements: Vec<Value> = vec![];
+    for credential in &verifiable_credentials {
+        let achievement = &credential["credentialSubject"]["achievement"];
+        if !achievements
+            .iter()
+            .any(|known| known["id"] == achievement["id"])
+        {
+            achievements.push(achievement.clone());
+        }
+    }
+
+    json!({
+        "@context": [
+            "https://www.w3.org/2018/credentials/v1",
+            CLR_CONTEXT,
+            OB3_CONTEXT,
+        ],
+        "id": format!("urn:uuid:{}", Uuid::new_v4()),
+        "type": ["VerifiableCredential", "ClrCredential"],
+        "issuer": {
+            "id": issuer_profile_url(),
+            "type": ["Profile"],
+            "name": ISSUER_PROFILE_NAME,
+            "otherIdentifier": issuer_other_identifiers(),
+        },
+        "issuanceDate": Utc::now().to_rfc3339(),
+        "name": translate(locale.as_deref(), "clr_name", &[("name", &profile_name)]),
+        "description": translate(
+            locale.as_deref(),
+            "clr_description",
+            &[
+                ("name", &profile_name),
+                ("count", &certificates.len().to_string()),
+            ],
+        ),
+        "credentialSubject": {
+            "id": subject_did,
+            "type": ["ClrSubject"],
+            "verifiableCredential": verifiable_credentials,
+            "achievement": achievements,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clr_credential() {
+        crate::config::set_test_env();
+
+        let certificate = CertificateData {
+            game_path_name: "Introduction to Rust".to_string(),
+            game_path_id: None,
+            total_challenges: 10,
+            solved_challenges: 8,
+            performance_percentage: 80,
+            profile_name: "Alice Rustacean".to_string(),
+            profile_id: Some("alice".to_string()),
+            date: Utc::now(),
+            requires_review: false,
+            locale: Some("de-DE".to_string()),
+            pass_threshold: None,
+            challenge_results: vec![],
+            summary_url: None,
+        };
+        let mut advanced = certificate.clone();
+        advanced.game_path_name = "Advanced Rust".to_string();
+        let subject_did: Url = "did:key:z6MkholderKey".parse().unwrap();
+
+        let clr = clr_credential(&[certificate.clone(), advanced, certificate], &subject_did);
+        assert_eq!(
+            clr["type"],
+            json!(["VerifiableCredential", "ClrCredential"])
+        );
+        assert_eq!(clr["credentialSubject"]["type"], json!(["ClrSubject"]));
+        assert_eq!(
+            clr["credentialSubject"]["verifiableCredential"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            clr["credentialSubject"]["achievement"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            clr["credentialSubject"]["verifiableCredential"][0]["credentialSubject"]["id"],
+            json!(subject_did)
+        );
+        assert!(clr["description"].as_str().unwrap().contains("Alle 3"));
+    }
+}
@@ -1,16 +1,109 @@
+use anyhow::{anyhow, Result};
 use dotenv::dotenv;
 use std::env;
+use std::path::PathBuf;
 
 pub fn load_config() -> (String, String) {
+    (load_private_key(), load_issuer_url())
+}
+
+/// Secret the issuer's signing key is derived from.
+pub fn load_private_key() -> String {
+    try_load_private_key().unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `load_private_key`, but an error instead of a panic for the command line.
+pub fn try_load_private_key() -> Result<String> {
+    dotenv().ok();
+
+    env::var("PRIVATE_KEY").map_err(|_| anyhow!("PRIVATE_KEY must be set in the .env file"))
+}
+
+/// Public URL of the issuer, used for documents the issuer hosts, like achievements.
+pub fn load_issuer_url() -> String {
+    dotenv().ok();
+
+    env::var("ISSUER_URL").expect("ISSUER_URL must be set in the .env file")
+}
+
+/// JSON file the issuer keeps its certificates and offers in, shared with the command line.
+/// Nothing is persisted if `STORAGE_FILE` is not set.
+pub fn load_storage_file() -> Option<PathBuf> {
     dotenv().ok();
 
-    let private_key = env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set in the .env file");
-    let issuer_url = env::var("ISSUER_URL").expect("ISSUER_URL must be set in the .env file");
+    env::var("STORAGE_FILE")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
 
-    (private_key, issuer_url)
+pub fn load_admin_token() -> Option<String> {
+    dotenv().ok();
+
+    env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Format of the access tokens issued at the token endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTokenFormat {
+    /// Random handles that are only known to the issuing instance.
+    Opaque,
+    /// Self-contained JWTs signed by the issuer.
+    Jwt,
 }
 
-#[derive(Debug)]
+pub fn load_access_token_format() -> AccessTokenFormat {
+    dotenv().ok();
+
+    match env::var("ACCESS_TOKEN_FORMAT").as_deref() {
+        Ok("jwt") => AccessTokenFormat::Jwt,
+        _ => AccessTokenFormat::Opaque,
+    }
+}
+
+/// Whether wallets must request encrypted credential responses, set `CREDENTIAL_RESPONSE_ENCRYPTION=required`.
+pub fn load_credential_response_encryption_required() -> bool {
+    dotenv().ok();
+
+    env::var("CREDENTIAL_RESPONSE_ENCRYPTION").as_deref() == Ok("required")
+}
+
+/// External OpenID Connect provider used to sign in learners.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub scopes: String,
+}
+
+/// Loads the OpenID Connect provider settings, federated login is disabled if `OIDC_DISCOVERY_URL`
+/// is not set. A discovery URL without client credentials is an error.
+pub fn load_oidc_config() -> Result<Option<OidcConfig>> {
+    dotenv().ok();
+
+    let Ok(discovery_url) = env::var("OIDC_DISCOVERY_URL") else {
+        return Ok(None);
+    };
+    let client_id = env::var("OIDC_CLIENT_ID")
+        .map_err(|_| anyhow!("OIDC_CLIENT_ID must be set for OIDC login"))?;
+    let client_secret = env::var("OIDC_CLIENT_SECRET")
+        .map_err(|_| anyhow!("OIDC_CLIENT_SECRET must be set for OIDC login"))?;
+    let issuer_url = load_issuer_url();
+
+    Ok(Some(OidcConfig {
+        discovery_url,
+        client_id,
+        client_secret,
+        redirect_uri: format!("{}/authorize/callback", issuer_url.trim_end_matches('/')),
+        scopes: env::var("OIDC_SCOPES").unwrap_or_else(|_| "openid email profile".to_string()),
+    }))
+}
+
+#[derive(Debug, Clone)]
 pub struct Config {
     pub private_key: String,
     pub issuer_url: Option<String>,
@@ -31,3 +124,11 @@ impl Default for Config {
         Self::new(private_key, issuer_url)
     }
 }
+
+/// Sets the issuer key and URL for tests that build or sign credentials, always to the same values
+/// so tests running in parallel agree on them.
+#[cfg(test)]
+pub fn set_test_env() {
+    env::set_var("PRIVATE_KEY", "this-is-a-very-UNSAFE-issuer-secret-key");
+    env::set_var("ISSUER_URL", "http://localhost:3000");
+}
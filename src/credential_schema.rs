@@ -0,0 +1,214 @@
+use crate::certificate_data::CERTIFICATE_CREDENTIAL_CONFIGURATION_ID;
+use crate::clr_data::CLR_CREDENTIAL_CONFIGURATION_ID;
+use crate::config::load_issuer_url;
+use anyhow::{anyhow, Result};
+use jsonschema::{Draft, JSONSchema};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+
+/// Type of `credentialSchema` entries, JSON schemas validated the way 1EdTech specifies.
+pub const CREDENTIAL_SCHEMA_TYPE: &str = "1EdTechJsonSchemaValidator2019";
+
+/// Credential configurations the issuer offers and the files they are defined in.
+pub const CREDENTIAL_CONFIGURATION_FILES: [(&str, &str); 2] = [
+    (
+        CERTIFICATE_CREDENTIAL_CONFIGURATION_ID,
+        "./assets/konnektoren_certificate_config.json",
+    ),
+    (
+        CLR_CREDENTIAL_CONFIGURATION_ID,
+        "./assets/konnektoren_clr_config.json",
+    ),
+];
+
+/// Field of a credential configuration file naming the JSON schema of its credentials, it is
+/// not part of the published issuer metadata.
+pub const CREDENTIAL_SCHEMA_FIELD: &str = "credential_schema";
+
+/// The 1EdTech schemas of Open Badges 3.0 and CLR 2.0, which the schemas of the credential
+/// configurations build on. They are resolved from these files by their `$id`, never fetched.
+pub const SPECIFICATION_SCHEMA_FILES: [&str; 2] = [
+    "./assets/schemas/ob_v3p0_achievementcredential_schema.json",
+    "./assets/schemas/clr_v2p0_clrcredential_schema.json",
+];
+
+struct CredentialSchema {
+    path: String,
+    validator: JSONSchema,
+}
+
+/// URL at which the issuer serves a schema file of its assets.
+fn schema_url(path: &str) -> String {
+    format!(
+        "{}/{}",
+        load_issuer_url().trim_end_matches('/'),
+        path.trim_start_matches("./")
+    )
+}
+
+/// Loads the JSON schema a credential configuration file references.
+fn load_credential_schema(configuration_path: &str) -> Result<CredentialSchema> {
+    let configuration: Value = serde_json::from_reader(File::open(configuration_path)?)?;
+    let schema_path = configuration[CREDENTIAL_SCHEMA_FIELD]
+        .as_str()
+        .ok_or_else(|| {
+            anyhow!(
+                "{} names no {}",
+                configuration_path,
+                CREDENTIAL_SCHEMA_FIELD
+            )
+        })?;
+    let mut schema: Value = serde_json::from_reader(File::open(schema_path)?)?;
+    // The relative `$id` stands for the URL the schema is served at, which depends on `ISSUER_URL`
+    if let Some(schema) = schema.as_object_mut() {
+        schema.remove("$id");
+    }
+    let mut options = JSONSchema::options();
+    options.with_draft(Draft::Draft7);
+    for path in SPECIFICATION_SCHEMA_FILES {
+        let document: Value = serde_json::from_reader(File::open(path)?)?;
+        let id = document["$id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("The schema {} has no $id", path))?
+            .to_string();
+        options.with_document(id, document);
+    }
+    let validator = options
+        .compile(&schema)
+        .map_err(|e| anyhow!("Invalid schema {}: {}", schema_path, e))?;
+    Ok(CredentialSchema {
+        path: schema_path.to_string(),
+        validator,
+    })
+}
+
+lazy_static! {
+    // Credentials of a configuration without a valid schema are never issued
+    static ref CREDENTIAL_SCHEMAS: HashMap<&'static str, CredentialSchema> =
+        CREDENTIAL_CONFIGURATION_FILES
+            .iter()
+            .filter_map(|(credential_configuration_id, path)| {
+                load_credential_schema(path)
+                    .map_err(|e| log::error!("No schema for {}: {}", credential_configuration_id, e))
+                    .ok()
+                    .map(|schema| (*credential_configuration_id, schema))
+            })
+            .collect();
+}
+
+/// Whether the schema of a credential configuration was loaded.
+pub fn has_credential_schema(credential_configuration_id: &str) -> bool {
+    CREDENTIAL_SCHEMAS.contains_key(credential_configuration_id)
+}
+
+/// The `credentialSchema` of credentials of a credential configuration, the schema as served
+/// by the issuer.
+pub fn credential_schema(credential_configuration_id: &str) -> Option<Value> {
+    CREDENTIAL_SCHEMAS
+        .get(credential_configuration_id)
+        .map(|schema| {
+            json!([{
+                "id": schema_url(&schema.path),
+                "type": CREDENTIAL_SCHEMA_TYPE,
+            }])
+        })
+}
+
+/// Validates a credential against the schema of its credential configuration.
+pub fn validate_credential(credential_configuration_id: &str, credential: &Value) -> Result<()> {
+    let schema = CREDENTIAL_SCHEMAS
+        .get(credential_configuration_id)
+        .ok_or_else(|| anyhow!("No schema for {}", credential_configuration_id))?;
+    schema.validator.validate(credential).map_err(|errors| {
+        let errors: Vec<String> = errors
+            .map(|error| format!("{} at {}", error, error.instance_path))
+            .collect();
+        anyhow!(
+            "Invalid {} credential: {}",
+            credential_configuration_id,
+            errors.join("; ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_data::{CertificateData, ChallengeResult};
+    use crate::clr_data::clr_credential;
+    use chrono::Utc;
+    use url::Url;
+
+    fn certificate() -> CertificateData {
+        CertificateData {
+            game_path_name: "Konnektoren".to_string(),
+            game_path_id: Some("konnektoren".to_string()),
+            total_challenges: 10,
+            solved_challenges: 8,
+            performance_percentage: 80,
+            profile_name: "Alice".to_string(),
+            profile_id: Some("alice".to_string()),
+            date: Utc::now(),
+            requires_review: false,
+            locale: Some("uk-UA".to_string()),
+            pass_threshold: Some(60),
+            challenge_results: vec![ChallengeResult {
+                challenge_id: "konnektoren-1".to_string(),
+                name: None,
+                performance_percentage: 90,
+            }],
+            summary_url: Some("https://konnektoren.help/results/42".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_schemas_of_credential_configurations() {
+        crate::config::set_test_env();
+        for (credential_configuration_id, _) in CREDENTIAL_CONFIGURATION_FILES {
+            let credential_schema = credential_schema(credential_configuration_id).unwrap();
+            assert!(credential_schema[0]["id"]
+                .as_str()
+                .unwrap()
+                .starts_with("http://localhost:3000/assets/schemas/konnektoren_"));
+        }
+        assert!(credential_schema("UniversityDegree").is_none());
+        assert!(load_credential_schema("./assets/university_degree_config.json").is_err());
+    }
+
+    #[test]
+    fn test_fixtures() {
+        let certificate: Value =
+            serde_json::from_str(include_str!("../assets/konnektoren_certificate.json")).unwrap();
+        validate_credential(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID, &certificate).unwrap();
+
+        let university_degree: Value =
+            serde_json::from_str(include_str!("../assets/university_degree.json")).unwrap();
+        let error =
+            validate_credential(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID, &university_degree)
+                .unwrap_err()
+                .to_string();
+        assert!(error.contains("credentialSubject"));
+    }
+
+    #[test]
+    fn test_issued_credentials() {
+        crate::config::set_test_env();
+        let subject_did: Url = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            .parse()
+            .unwrap();
+
+        let mut credential = certificate().to_verifiable_credential(&subject_did);
+        credential["credentialSchema"] =
+            credential_schema(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID).unwrap();
+        validate_credential(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID, &credential).unwrap();
+
+        let clr = clr_credential(&[certificate(), certificate()], &subject_did);
+        validate_credential(CLR_CREDENTIAL_CONFIGURATION_ID, &clr).unwrap();
+        assert!(validate_credential(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID, &clr).is_err());
+
+        credential["credentialSubject"]["result"][0]["status"] = json!("Passed");
+        assert!(validate_credential(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID, &credential).is_err());
+    }
+}
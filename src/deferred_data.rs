@@ -0,0 +1,75 @@
+use crate::certificate_data::CertificateData;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Polling interval in seconds suggested to wallets while a review is pending.
+pub const DEFERRED_POLLING_INTERVAL: u64 = 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A credential request that waits for a teacher's review before it is signed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingReview {
+    pub transaction_id: String,
+    pub certificate_id: String,
+    pub certificate: CertificateData,
+    pub subject_did: String,
+    pub status: ReviewStatus,
+    pub reason: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    /// Hash of the access token the credential was requested with, only that token redeems the review.
+    #[serde(default)]
+    pub access_token_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeferredCredentialRequest {
+    pub transaction_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ReviewDecision {
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeferredCredentialErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+}
+
+impl DeferredCredentialErrorResponse {
+    pub fn issuance_pending() -> Self {
+        Self {
+            error: "issuance_pending".to_string(),
+            error_description: None,
+            interval: Some(DEFERRED_POLLING_INTERVAL),
+        }
+    }
+
+    pub fn invalid_transaction_id() -> Self {
+        Self {
+            error: "invalid_transaction_id".to_string(),
+            error_description: None,
+            interval: None,
+        }
+    }
+
+    pub fn credential_request_denied(reason: Option<String>) -> Self {
+        Self {
+            error: "credential_request_denied".to_string(),
+            error_description: reason,
+            interval: None,
+        }
+    }
+}
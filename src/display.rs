@@ -0,0 +1,194 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::{env, fs::File};
+use url::Url;
+
+/// Display configuration used when `DISPLAY_FILE` is not set.
+pub const DEFAULT_DISPLAY_FILE: &str = "./assets/display.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Logo {
+    pub uri: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alt_text: Option<String>,
+}
+
+/// Localized display properties of the issuer or a credential, as defined by OID4VCI.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Display {
+    pub name: String,
+    pub locale: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<Logo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_image: Option<Logo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+}
+
+/// Issuer display and display of each credential configuration, keyed by configuration id.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub issuer: Vec<Display>,
+    #[serde(default)]
+    pub credentials: HashMap<String, Vec<Display>>,
+}
+
+/// Checks for a BCP 47 language tag like `de`, `de-DE` or `uk-UA`.
+fn is_language_tag(locale: &str) -> bool {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// Checks for a CSS hex color like `#fff` or `#ff7e00`.
+fn is_color(color: &str) -> bool {
+    color.strip_prefix('#').map_or(false, |hex| {
+        (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+fn validate_displays(context: &str, displays: &[Display]) -> Vec<String> {
+    let mut errors = vec![];
+    if displays.is_empty() {
+        errors.push(format!("{}: at least one display is required", context));
+    }
+    let mut locales = HashSet::new();
+    for display in displays {
+        let context = format!("{} ({})", context, display.locale);
+        if display.name.trim().is_empty() {
+            errors.push(format!("{}: name must not be empty", context));
+        }
+        if !is_language_tag(&display.locale) {
+            errors.push(format!("{}: locale is not a BCP 47 language tag", context));
+        }
+        if !locales.insert(display.locale.to_lowercase()) {
+            errors.push(format!("{}: locale is configured twice", context));
+        }
+        for (field, color) in [
+            ("background_color", &display.background_color),
+            ("text_color", &display.text_color),
+        ] {
+            if color.as_deref().map_or(false, |color| !is_color(color)) {
+                errors.push(format!("{}: {} is not a CSS hex color", context, field));
+            }
+        }
+        for (field, logo) in [
+            ("logo", &display.logo),
+            ("background_image", &display.background_image),
+        ] {
+            if logo.as_ref().map_or(false, |logo| {
+                !matches!(logo.uri.scheme(), "https" | "http" | "data")
+            }) {
+                errors.push(format!(
+                    "{}: {} must be an http(s) or data URI",
+                    context, field
+                ));
+            }
+        }
+    }
+    errors
+}
+
+impl DisplayConfig {
+    /// Validates the configuration against the display properties defined by OID4VCI.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = validate_displays("issuer", &self.issuer);
+        for (credential_configuration_id, displays) in &self.credentials {
+            errors.extend(validate_displays(credential_configuration_id, displays));
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "Invalid display configuration: {}",
+                errors.join("; ")
+            )),
+        }
+    }
+
+    pub fn issuer_display(&self) -> Vec<Value> {
+        self.issuer
+            .iter()
+            .filter_map(|display| serde_json::to_value(display).ok())
+            .collect()
+    }
+
+    pub fn credential_display(&self, credential_configuration_id: &str) -> Option<Vec<Value>> {
+        self.credentials
+            .get(credential_configuration_id)
+            .map(|displays| {
+                displays
+                    .iter()
+                    .filter_map(|display| serde_json::to_value(display).ok())
+                    .collect()
+            })
+    }
+}
+
+/// Loads and validates the display configuration from the JSON file referenced by `DISPLAY_FILE`.
+pub fn load_display_config() -> Result<DisplayConfig> {
+    let path = env::var("DISPLAY_FILE").unwrap_or_else(|_| DEFAULT_DISPLAY_FILE.to_string());
+    let config: DisplayConfig = serde_json::from_reader(File::open(&path)?)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path, e))?;
+    config.validate()?;
+    log::info!(
+        "Loaded display configuration for {} issuer locales from {}",
+        config.issuer.len(),
+        path
+    );
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_display_config_is_valid() {
+        let config: DisplayConfig =
+            serde_json::from_reader(File::open(DEFAULT_DISPLAY_FILE).unwrap()).unwrap();
+        config.validate().unwrap();
+        assert!(config
+            .issuer
+            .iter()
+            .any(|display| display.locale == "de-DE"));
+    }
+
+    #[test]
+    fn test_credential_configuration_displays_are_valid() {
+        for (_, path) in crate::credential_schema::CREDENTIAL_CONFIGURATION_FILES {
+            let configuration: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+            let displays: Vec<Display> =
+                serde_json::from_value(configuration["display"].clone()).unwrap();
+            assert_eq!(validate_displays(path, &displays), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn test_invalid_display_config() {
+        let config: DisplayConfig = serde_json::from_value(serde_json::json!({
+            "issuer": [
+                { "name": "Konnektoren", "locale": "de-DE", "text_color": "orange" },
+                { "name": "", "locale": "de-DE" }
+            ]
+        }))
+        .unwrap();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("text_color"));
+        assert!(error.contains("name must not be empty"));
+        assert!(error.contains("configured twice"));
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Name of the HTTP header carrying the DPoP proof.
+pub const DPOP_HEADER: &str = "DPoP";
+
+/// Token type of access tokens that are bound to the wallet's key.
+pub const DPOP_TOKEN_TYPE: &str = "DPoP";
+
+/// Signing algorithms accepted for DPoP proofs.
+pub const DPOP_SIGNING_ALG_VALUES_SUPPORTED: [&str; 2] = ["ES256", "EdDSA"];
+
+/// How far the `iat` of a DPoP proof may deviate from the server time in seconds.
+pub const DPOP_PROOF_MAX_AGE_SECONDS: i64 = 300;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct DpopClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    ath: Option<String>,
+}
+
+/// A validated DPoP proof (RFC 9449).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DpopProof {
+    /// JWK SHA-256 thumbprint of the wallet's public key.
+    pub jkt: String,
+    pub jti: String,
+}
+
+/// Computes the JWK SHA-256 thumbprint of a public key (RFC 7638).
+pub fn jwk_thumbprint(jwk: &Jwk) -> Result<String> {
+    let jwk = serde_json::to_value(jwk)?;
+    let members: &[&str] = match jwk["kty"].as_str() {
+        Some("EC") => &["crv", "kty", "x", "y"],
+        Some("OKP") => &["crv", "kty", "x"],
+        Some("RSA") => &["e", "kty", "n"],
+        _ => return Err(anyhow!("Unsupported key type")),
+    };
+    let mut canonical = BTreeMap::new();
+    for member in members {
+        let value = jwk[*member]
+            .as_str()
+            .ok_or_else(|| anyhow!("JWK is missing the {} member", member))?;
+        canonical.insert(*member, value);
+    }
+    Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(serde_json::to_string(&canonical)?)))
+}
+
+/// Hash of the access token as expected in the `ath` claim.
+pub fn access_token_hash(access_token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()))
+}
+
+/// Validates a DPoP proof for a request to `url` with the HTTP `method`. Proofs sent to
+/// resource endpoints must also carry the hash of the `access_token` they are presented with.
+pub fn validate_dpop_proof(
+    proof: &str,
+    method: &str,
+    url: &Url,
+    access_token: Option<&str>,
+) -> Result<DpopProof> {
+    let header = decode_header(proof)?;
+    if header.typ.as_deref() != Some("dpop+jwt") {
+        return Err(anyhow!("DPoP proof must be of type dpop+jwt"));
+    }
+    if !matches!(header.alg, Algorithm::ES256 | Algorithm::EdDSA) {
+        return Err(anyhow!("Unsupported DPoP signing algorithm"));
+    }
+    let jwk = header
+        .jwk
+        .ok_or_else(|| anyhow!("DPoP proof does not contain a public key"))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    let claims = decode::<DpopClaims>(proof, &DecodingKey::from_jwk(&jwk)?, &validation)?.claims;
+
+    if !claims.htm.eq_ignore_ascii_case(method) {
+        return Err(anyhow!("DPoP proof was issued for another method"));
+    }
+    let mut expected_htu = url.clone();
+    expected_htu.set_query(None);
+    expected_htu.set_fragment(None);
+    let htu = Url::parse(&claims.htu)?;
+    if htu.as_str().trim_end_matches('/') != expected_htu.as_str().trim_end_matches('/') {
+        return Err(anyhow!("DPoP proof was issued for another URL"));
+    }
+    if (Utc::now().timestamp() - claims.iat).abs() > DPOP_PROOF_MAX_AGE_SECONDS {
+        return Err(anyhow!("DPoP proof is expired"));
+    }
+    if let Some(access_token) = access_token {
+        if claims.ath.as_deref() != Some(access_token_hash(access_token).as_str()) {
+            return Err(anyhow!("DPoP proof is not bound to the access token"));
+        }
+    }
+
+    Ok(DpopProof {
+        jkt: jwk_thumbprint(&jwk)?,
+        jti: claims.jti,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jwk_thumbprint() {
+        // Example from RFC 7638, section 3.1
+        let jwk: Jwk = serde_json::from_value(serde_json::json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29"
+        }))
+        .unwrap();
+
+        assert_eq!(
+            jwk_thumbprint(&jwk).unwrap(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use p256::ecdh::EphemeralSecret;
+use p256::PublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Key management algorithms supported for credential response encryption.
+pub const CREDENTIAL_RESPONSE_ALG_VALUES_SUPPORTED: [&str; 1] = ["ECDH-ES"];
+
+/// Content encryption algorithms supported for credential response encryption.
+pub const CREDENTIAL_RESPONSE_ENC_VALUES_SUPPORTED: [&str; 1] = ["A256GCM"];
+
+/// Encryption parameters a wallet sends with its credential request.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialResponseEncryption {
+    pub jwk: Value,
+    pub alg: String,
+    pub enc: String,
+}
+
+/// Concat KDF of RFC 7518, section 4.6.2, for a 256 bit key in direct key agreement mode.
+fn concat_kdf(shared_secret: &[u8], enc: &str) -> [u8; 32] {
+    let mut other_info = Vec::new();
+    for field in [enc.as_bytes(), b"", b""] {
+        other_info.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        other_info.extend_from_slice(field);
+    }
+    other_info.extend_from_slice(&256u32.to_be_bytes());
+
+    let mut hasher = Sha256::new();
+    hasher.update(1u32.to_be_bytes());
+    hasher.update(shared_secret);
+    hasher.update(&other_info);
+    hasher.finalize().into()
+}
+
+impl CredentialResponseEncryption {
+    /// Reads the `credential_response_encryption` parameters of a credential request and checks
+    /// them, so nothing is issued for a response the wallet could not decrypt.
+    pub fn from_request(request: &Value, required: bool) -> Result<Option<Self>> {
+        let encryption: Self = match request.get("credential_response_encryption") {
+            Some(encryption) => serde_json::from_value(encryption.clone())?,
+            None if required => return Err(anyhow!("Credential response encryption is required")),
+            None => return Ok(None),
+        };
+        encryption.recipient()?;
+        Ok(Some(encryption))
+    }
+
+    /// The wallet's public key, if the algorithms are supported.
+    fn recipient(&self) -> Result<PublicKey> {
+        if !CREDENTIAL_RESPONSE_ALG_VALUES_SUPPORTED.contains(&self.alg.as_str()) {
+            return Err(anyhow!("Unsupported alg {}", self.alg));
+        }
+        if !CREDENTIAL_RESPONSE_ENC_VALUES_SUPPORTED.contains(&self.enc.as_str()) {
+            return Err(anyhow!("Unsupported enc {}", self.enc));
+        }
+        Ok(PublicKey::from_jwk_str(&self.jwk.to_string())?)
+    }
+
+    /// Encrypts the credential response for the wallet's key as compact JWE.
+    pub fn encrypt(&self, payload: &[u8]) -> Result<String> {
+        let recipient = self.recipient()?;
+
+        let ephemeral_secret = EphemeralSecret::random(&mut OsRng);
+        let shared_secret = ephemeral_secret.diffie_hellman(&recipient);
+        let key = concat_kdf(shared_secret.raw_secret_bytes(), &self.enc);
+
+        let mut header = json!({
+            "alg": self.alg,
+            "enc": self.enc,
+            "epk": serde_json::from_str::<Value>(&ephemeral_secret.public_key().to_jwk_string())?,
+        });
+        if let Some(kid) = self.jwk.get("kid") {
+            header["kid"] = kid.clone();
+        }
+        let protected_header = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&header)?);
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let iv = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut ciphertext = cipher
+            .encrypt(
+                &iv,
+                Payload {
+                    msg: payload,
+                    aad: protected_header.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("Failed to encrypt the credential response"))?;
+        let tag = ciphertext.split_off(ciphertext.len() - 16);
+
+        Ok(format!(
+            "{}..{}.{}.{}",
+            protected_header,
+            URL_SAFE_NO_PAD.encode(iv),
+            URL_SAFE_NO_PAD.encode(ciphertext),
+            URL_SAFE_NO_PAD.encode(tag)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::Nonce;
+    use p256::ecdh::diffie_hellman;
+    use p256::SecretKey;
+
+    #[test]
+    fn test_encrypt_credential_response() {
+        let wallet_key = SecretKey::random(&mut OsRng);
+        let encryption = CredentialResponseEncryption {
+            jwk: serde_json::from_str(&wallet_key.public_key().to_jwk_string()).unwrap(),
+            alg: "ECDH-ES".to_string(),
+            enc: "A256GCM".to_string(),
+        };
+
+        let jwe = encryption.encrypt(br#"{"credential":"ey..."}"#).unwrap();
+        let parts: Vec<&str> = jwe.split('.').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[1].is_empty());
+
+        // Decrypt as the wallet would
+        let header: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[0]).unwrap()).unwrap();
+        let epk = PublicKey::from_jwk_str(&header["epk"].to_string()).unwrap();
+        let shared_secret = diffie_hellman(wallet_key.to_nonzero_scalar(), epk.as_affine());
+        let key = concat_kdf(shared_secret.raw_secret_bytes(), "A256GCM");
+
+        let iv = URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        let mut ciphertext = URL_SAFE_NO_PAD.decode(parts[3]).unwrap();
+        ciphertext.extend(URL_SAFE_NO_PAD.decode(parts[4]).unwrap());
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(&iv),
+                Payload {
+                    msg: &ciphertext,
+                    aad: parts[0].as_bytes(),
+                },
+            )
+            .unwrap();
+        assert_eq!(plaintext, br#"{"credential":"ey..."}"#);
+    }
+
+    #[test]
+    fn test_encryption_parameters_of_request() {
+        let jwk: Value =
+            serde_json::from_str(&SecretKey::random(&mut OsRng).public_key().to_jwk_string())
+                .unwrap();
+        let request = |alg: &str, enc: &str, jwk: &Value| {
+            json!({
+                "format": "jwt_vc_json",
+                "credential_response_encryption": { "jwk": jwk, "alg": alg, "enc": enc },
+            })
+        };
+
+        assert!(CredentialResponseEncryption::from_request(
+            &request("ECDH-ES", "A256GCM", &jwk),
+            true
+        )
+        .unwrap()
+        .is_some());
+        assert!(
+            CredentialResponseEncryption::from_request(&json!({}), false)
+                .unwrap()
+                .is_none()
+        );
+        assert!(CredentialResponseEncryption::from_request(&json!({}), true).is_err());
+        assert!(CredentialResponseEncryption::from_request(
+            &request("RSA-OAEP", "A256GCM", &jwk),
+            false
+        )
+        .is_err());
+        assert!(CredentialResponseEncryption::from_request(
+            &request("ECDH-ES", "A128CBC-HS256", &jwk),
+            false
+        )
+        .is_err());
+        assert!(CredentialResponseEncryption::from_request(
+            &request(
+                "ECDH-ES",
+                "A256GCM",
+                &json!({ "kty": "EC", "crv": "P-256" })
+            ),
+            false
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::achievements::{achievement_definitions, OB3_CONTEXT};
+use crate::issuer_key::{did_key_decoding_key, sign_with_key};
+use crate::issuer_profile::issuer_profile_url;
+use crate::partners::Partner;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// JWT `typ` of endorsements signed on behalf of a partner.
+const ENDORSEMENT_TOKEN_TYPE: &str = "JWT";
+
+/// Request to record a partner's endorsement of an achievement or of the issuer profile.
+///
+/// Partners either send an `endorsement_jwt` they signed themselves, or a `target_id` and
+/// `comment` the issuer signs on their behalf with the partner's configured key.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EndorsementRequest {
+    pub partner_id: String,
+    #[serde(default)]
+    pub target_id: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub endorsement_jwt: Option<String>,
+}
+
+/// A stored endorsement, embedded into the credentials referencing its target.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Endorsement {
+    pub id: String,
+    pub partner_id: String,
+    /// Achievement or issuer profile URL the endorsement is about.
+    pub target_id: String,
+    pub credential: Value,
+    pub jwt: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Claims of an `EndorsementCredential` as VC-JWT.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EndorsementClaims {
+    pub iss: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    pub vc: Value,
+}
+
+/// Whether the issuer publishes the endorsed achievement or profile.
+pub fn is_endorsable(target_id: &str) -> bool {
+    target_id == issuer_profile_url()
+        || achievement_definitions()
+            .iter()
+            .any(|achievement| achievement.url() == target_id)
+}
+
+/// Builds an OB3 `EndorsementCredential` of the partner for the target.
+pub fn endorsement_credential(
+    partner: &Partner,
+    partner_did: &str,
+    target_id: &str,
+    comment: &str,
+) -> Value {
+    let mut issuer = json!({
+        "id": partner_did,
+        "type": ["Profile"],
+        "name": partner.name,
+    });
+    if let Some(url) = &partner.url {
+        issuer["url"] = json!(url);
+    }
+    json!({
+        "@context": [
+            "https://www.w3.org/2018/credentials/v1",
+            OB3_CONTEXT,
+        ],
+        "id": format!("urn:uuid:{}", Uuid::new_v4()),
+        "type": ["VerifiableCredential", "EndorsementCredential"],
+        "name": format!("Endorsement by {}", partner.name),
+        "issuer": issuer,
+        "issuanceDate": Utc::now().to_rfc3339(),
+        "credentialSubject": {
+            "id": target_id,
+            "type": ["EndorsementSubject"],
+            "endorsementComment": comment,
+        },
+    })
+}
+
+/// Signs an endorsement on behalf of a partner whose key the issuer holds.
+pub fn sign_endorsement(partner: &Partner, target_id: &str, comment: &str) -> Result<Endorsement> {
+    let key = partner
+        .signing_key()
+        .ok_or_else(|| anyhow!("No key configured for partner {}", partner.id))?;
+    let partner_did = partner.did().unwrap_or_default();
+    let credential = endorsement_credential(partner, &partner_did, target_id, comment);
+    let claims = EndorsementClaims {
+        iss: partner_did,
+        sub: Some(target_id.to_string()),
+        jti: credential["id"].as_str().map(str::to_string),
+        nbf: Some(Utc::now().timestamp()),
+        vc: credential.clone(),
+    };
+    let jwt = sign_with_key(&key, ENDORSEMENT_TOKEN_TYPE, &claims)?;
+    Ok(Endorsement {
+        id: Uuid::new_v4().to_string(),
+        partner_id: partner.id.clone(),
+        target_id: target_id.to_string(),
+        credential,
+        jwt,
+        created_at: Utc::now(),
+    })
+}
+
+/// Verifies an endorsement a partner signed with their `did:key`.
+pub fn verify_endorsement_jwt(partner: &Partner, jwt: &str) -> Result<Endorsement> {
+    let partner_did = partner
+        .did()
+        .ok_or_else(|| anyhow!("No DID configured for partner {}", partner.id))?;
+    let header = decode_header(jwt)?;
+    if header.alg != Algorithm::EdDSA {
+        return Err(anyhow!("Endorsements must be signed with EdDSA"));
+    }
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.validate_aud = false;
+    validation.set_required_spec_claims(&["iss"]);
+    validation.set_issuer(&[&partner_did]);
+    let claims =
+        decode::<EndorsementClaims>(jwt, &did_key_decoding_key(&partner_did)?, &validation)?.claims;
+
+    let credential = claims.vc;
+    let is_endorsement = credential["type"].as_array().map_or(false, |types| {
+        types.contains(&json!("EndorsementCredential"))
+    });
+    if !is_endorsement {
+        return Err(anyhow!("Not an EndorsementCredential"));
+    }
+    // The credential must not claim another issuer than the partner who signed it
+    let issuer_id = credential["issuer"]["id"]
+        .as_str()
+        .or_else(|| credential["issuer"].as_str());
+    if issuer_id != Some(partner_did.as_str()) {
+        return Err(anyhow!(
+            "The endorsement's issuer {:?} is not the partner {}",
+            issuer_id,
+            partner_did
+        ));
+    }
+    let target_id = credential["credentialSubject"]["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("The endorsement has no credentialSubject.id"))?
+        .to_string();
+    if !is_endorsable(&target_id) {
+        return Err(anyhow!("Unknown endorsement target {}", target_id));
+    }
+    Ok(Endorsement {
+        id: Uuid::new_v4().to_string(),
+        partner_id: partner.id.clone(),
+        target_id,
+        credential,
+        jwt: jwt.to_string(),
+        created_at: Utc::now(),
+    })
+}
+
+/// Adds the JWTs of the endorsements of `target` to its `endorsementJwt`.
+pub fn embed_endorsements(target: &mut Value, endorsements: &[Endorsement]) {
+    let jwts: Vec<&str> = endorsements
+        .iter()
+        .filter(|endorsement| target["id"].as_str() == Some(endorsement.target_id.as_str()))
+        .map(|endorsement| endorsement.jwt.as_str())
+        .collect();
+    if !jwts.is_empty() {
+        target["endorsementJwt"] = json!(jwts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify_endorsement() {
+        crate::config::set_test_env();
+        let partner = Partner {
+            id: "sprachschule".to_string(),
+            name: "Sprachschule Berlin".to_string(),
+            url: Some("https://sprachschule.example".to_string()),
+            did: None,
+            private_key: Some("this-is-a-very-UNSAFE-partner-secret-key".to_string()),
+        };
+        let target_id = achievement_definitions()[0].url();
+
+        let endorsement = sign_endorsement(&partner, &target_id, "Great course").unwrap();
+        assert_eq!(
+            endorsement.credential["credentialSubject"]["endorsementComment"],
+            json!("Great course")
+        );
+
+        let verified = verify_endorsement_jwt(&partner, &endorsement.jwt).unwrap();
+        assert_eq!(verified.target_id, target_id);
+
+        // Signed by the partner, but naming someone else as issuer
+        let key = partner.signing_key().unwrap();
+        let mut credential = endorsement.credential.clone();
+        credential["issuer"]["id"] =
+            json!("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let claims = EndorsementClaims {
+            iss: partner.did().unwrap(),
+            sub: Some(target_id.clone()),
+            jti: None,
+            nbf: None,
+            vc: credential,
+        };
+        let impersonating = sign_with_key(&key, ENDORSEMENT_TOKEN_TYPE, &claims).unwrap();
+        assert!(verify_endorsement_jwt(&partner, &impersonating).is_err());
+
+        let other = Partner {
+            private_key: Some("another-UNSAFE-secret".to_string()),
+            ..partner
+        };
+        assert!(verify_endorsement_jwt(&other, &endorsement.jwt).is_err());
+
+        let mut achievement = json!({ "id": target_id });
+        embed_endorsements(&mut achievement, &[endorsement.clone()]);
+        assert_eq!(achievement["endorsementJwt"], json!([endorsement.jwt]));
+    }
+}
@@ -0,0 +1,80 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// Language of the credential texts if the learner's locale is unknown or not translated.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+lazy_static! {
+    static ref CATALOGS: HashMap<&'static str, HashMap<String, String>> = [
+        ("de", include_str!("../assets/i18n/de.json")),
+        ("en", include_str!("../assets/i18n/en.json")),
+        ("uk", include_str!("../assets/i18n/uk.json")),
+    ]
+    .into_iter()
+    .map(|(language, catalog)| (language, serde_json::from_str(catalog).unwrap()))
+    .collect();
+}
+
+/// Resolves a locale like `de-DE` to the language of a translation catalog.
+pub fn resolve_language(locale: Option<&str>) -> &'static str {
+    locale
+        .and_then(|locale| locale.split(['-', '_']).next())
+        .map(|language| language.to_lowercase())
+        .and_then(|language| {
+            CATALOGS
+                .keys()
+                .find(|catalog| **catalog == language)
+                .copied()
+        })
+        .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// Languages of the translation catalogs, sorted.
+pub fn languages() -> Vec<&'static str> {
+    let mut languages: Vec<&'static str> = CATALOGS.keys().copied().collect();
+    languages.sort();
+    languages
+}
+
+/// Translates `key` into the language of `locale`, replacing `{placeholders}` with `args`.
+pub fn translate(locale: Option<&str>, key: &str, args: &[(&str, &str)]) -> String {
+    let text = CATALOGS[resolve_language(locale)]
+        .get(key)
+        .or_else(|| CATALOGS[DEFAULT_LANGUAGE].get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string());
+    args.iter().fold(text, |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_have_the_same_keys() {
+        for (language, catalog) in CATALOGS.iter() {
+            let mut keys: Vec<&String> = catalog.keys().collect();
+            let mut default_keys: Vec<&String> = CATALOGS[DEFAULT_LANGUAGE].keys().collect();
+            keys.sort();
+            default_keys.sort();
+            assert_eq!(keys, default_keys, "catalog {} is incomplete", language);
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(resolve_language(Some("de-DE")), "de");
+        assert_eq!(resolve_language(Some("fr-FR")), DEFAULT_LANGUAGE);
+        assert_eq!(languages(), vec!["de", "en", "uk"]);
+        assert_eq!(
+            translate(
+                Some("de-DE"),
+                "criteria_narrative",
+                &[("solved", "8"), ("total", "10"), ("performance", "80")]
+            ),
+            "8 von 10 Aufgaben mit einer Leistung von 80% abgeschlossen"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use crate::config::load_private_key;
+use anyhow::{anyhow, Result};
+use did_key::{generate, resolve, DIDCore, Ed25519KeyPair, KeyMaterial, PatchedKeyPair};
+use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use serde::Serialize;
+
+/// PKCS#8 prefix of an Ed25519 private key, followed by the 32 byte seed.
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// The issuer's Ed25519 key, derived from `PRIVATE_KEY`.
+pub fn issuer_key() -> PatchedKeyPair {
+    let priv_key = load_private_key();
+    ed25519_key_from_secret(&priv_key)
+}
+
+/// Derives an Ed25519 `did:key` from a secret, the way the issuer's key is derived from `PRIVATE_KEY`.
+pub fn ed25519_key_from_secret(secret: &str) -> PatchedKeyPair {
+    generate::<Ed25519KeyPair>(Some(secret.as_bytes()))
+}
+
+/// Verification method of a `did:key`, used as `kid` of JWTs signed with the key.
+pub fn key_id(key: &PatchedKeyPair) -> String {
+    let did = key.get_did_document(did_key::Config::default()).id;
+    format!("{}#{}", did, did.trim_start_matches("did:key:"))
+}
+
+/// Verification method of the issuer's `did:key`, used as `kid` of JWTs signed by the issuer.
+pub fn issuer_key_id() -> String {
+    key_id(&issuer_key())
+}
+
+/// Signs `claims` as JWT of type `typ` with an Ed25519 `did:key`.
+pub fn sign_with_key<T: Serialize>(key: &PatchedKeyPair, typ: &str, claims: &T) -> Result<String> {
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.typ = Some(typ.to_string());
+    header.kid = Some(key_id(key));
+
+    let der = [ED25519_PKCS8_PREFIX.as_slice(), &key.private_key_bytes()].concat();
+    Ok(encode(&header, claims, &EncodingKey::from_ed_der(&der))?)
+}
+
+/// Signs `claims` as JWT of type `typ` with the issuer's Ed25519 key.
+pub fn sign_with_issuer_key<T: Serialize>(typ: &str, claims: &T) -> Result<String> {
+    sign_with_key(&issuer_key(), typ, claims)
+}
+
+/// Key to validate JWTs signed with the Ed25519 key of a `did:key`, e.g. `did:key:z6Mk...`.
+pub fn did_key_decoding_key(did: &str) -> Result<DecodingKey> {
+    let did = did.split('#').next().unwrap_or_default();
+    let key = resolve(did).map_err(|e| anyhow!("Failed to resolve {}: {:?}", did, e))?;
+    Ok(DecodingKey::from_ed_der(&key.public_key_bytes()))
+}
+
+/// Key to validate JWTs signed by the issuer.
+pub fn issuer_decoding_key() -> DecodingKey {
+    DecodingKey::from_ed_der(&issuer_key().public_key_bytes())
+}
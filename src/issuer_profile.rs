@@ -0,0 +1,95 @@
+use crate::achievements::OB3_CONTEXT;
+use crate::config::load_issuer_url;
+use crate::display::{load_display_config, Display};
+use crate::i18n::resolve_language;
+use crate::storage::get_issuer_did;
+use serde_json::{json, Value};
+
+/// Slug of the issuer profile at `/ob3/issuers/{slug}`.
+pub const ISSUER_PROFILE_SLUG: &str = "konnektoren";
+
+/// Name of the issuer in the Open Badges profile.
+pub const ISSUER_PROFILE_NAME: &str = "Konnektoren";
+
+/// Homepage of the issuer.
+pub const ISSUER_HOMEPAGE: &str = "https://konnektoren.help";
+
+/// URL at which the issuer publishes its Open Badges profile, used as `issuer.id` of credentials.
+pub fn issuer_profile_url() -> String {
+    format!(
+        "{}/ob3/issuers/{}",
+        load_issuer_url().trim_end_matches('/'),
+        ISSUER_PROFILE_SLUG
+    )
+}
+
+/// The issuer's DID, which signs the credentials, as identifier of the profile.
+pub fn issuer_other_identifiers() -> Value {
+    json!([{
+        "type": "IdentifierEntry",
+        "identifier": get_issuer_did(),
+        "identifierType": "identifier",
+    }])
+}
+
+/// Issuer display in the language of `locale`, falling back to the first configured display.
+fn issuer_display(locale: Option<&str>) -> Option<Display> {
+    let displays = match load_display_config() {
+        Ok(config) => config.issuer,
+        Err(e) => {
+            log::warn!("Failed to load the display configuration: {}", e);
+            return None;
+        }
+    };
+    let language = resolve_language(locale);
+    displays
+        .iter()
+        .find(|display| resolve_language(Some(&display.locale)) == language)
+        .or_else(|| displays.first())
+        .cloned()
+}
+
+/// The Open Badges 3.0 `Profile` of the issuer as JSON-LD document.
+pub fn issuer_profile(locale: Option<&str>) -> Value {
+    let mut profile = json!({
+        "@context": [OB3_CONTEXT],
+        "id": issuer_profile_url(),
+        "type": ["Profile"],
+        "name": ISSUER_PROFILE_NAME,
+        "url": ISSUER_HOMEPAGE,
+        "otherIdentifier": issuer_other_identifiers(),
+    });
+    if let Some(display) = issuer_display(locale) {
+        if let Some(description) = display.description {
+            profile["description"] = json!(description);
+        }
+        if let Some(logo) = display.logo {
+            profile["image"] = json!({
+                "id": logo.uri,
+                "type": "Image",
+                "caption": logo.alt_text,
+            });
+        }
+        profile["inLanguage"] = json!(display.locale);
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issuer_profile() {
+        crate::config::set_test_env();
+
+        let profile = issuer_profile(Some("de"));
+        assert_eq!(profile["id"], json!(issuer_profile_url()));
+        assert_eq!(profile["type"], json!(["Profile"]));
+        assert_eq!(profile["inLanguage"], json!("de-DE"));
+        assert_eq!(
+            profile["otherIdentifier"][0]["identifier"],
+            json!(get_issuer_did())
+        );
+    }
+}
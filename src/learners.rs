@@ -0,0 +1,67 @@
+use crate::oidc::FederatedIdentity;
+use anyhow::Result;
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use serde::{Deserialize, Serialize};
+use std::{env, fs::File};
+
+/// A Konnektoren learner that can sign in during the authorization code flow.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Learner {
+    pub profile_id: String,
+    pub name: String,
+    /// Argon2 password hash in PHC string format, not needed for federated learners.
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    /// Subject identifier at the OpenID Connect provider.
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Loads the learners from the JSON file referenced by `LEARNERS_FILE`.
+pub fn load_learners() -> Result<Vec<Learner>> {
+    match env::var("LEARNERS_FILE") {
+        Ok(path) => Ok(serde_json::from_reader(File::open(path)?)?),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+pub fn authenticate_learner(profile_id: &str, password: &str) -> Option<Learner> {
+    let learners = load_learners()
+        .map_err(|e| log::error!("Failed to load learners: {}", e))
+        .ok()?;
+    let learner = learners
+        .into_iter()
+        .find(|learner| learner.profile_id == profile_id)?;
+    let password_hash = PasswordHash::new(learner.password_hash.as_deref()?)
+        .map_err(|e| log::error!("Invalid password hash for {}: {}", profile_id, e))
+        .ok()?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &password_hash)
+        .ok()
+        .map(|_| learner)
+}
+
+/// Maps an identity from the OpenID Connect provider to a Konnektoren learner, first by
+/// subject and then by verified email address.
+pub fn find_federated_learner(identity: &FederatedIdentity) -> Option<Learner> {
+    let learners = load_learners()
+        .map_err(|e| log::error!("Failed to load learners: {}", e))
+        .ok()?;
+    let by_subject = learners
+        .iter()
+        .find(|learner| learner.subject.as_deref() == Some(identity.subject.as_str()));
+    let by_email = || {
+        let email = identity
+            .email
+            .as_deref()
+            .filter(|_| identity.email_verified)?;
+        learners.iter().find(|learner| {
+            learner.email.as_deref().map_or(false, |learner_email| {
+                learner_email.eq_ignore_ascii_case(email)
+            })
+        })
+    };
+    by_subject.or_else(by_email).cloned()
+}
@@ -1,26 +1,76 @@
+mod access_token;
+mod achievements;
+mod authorization_data;
 mod certificate_data;
+mod cli;
+mod clients;
+mod clr_data;
 mod config;
+mod credential_schema;
+mod deferred_data;
+mod display;
+mod dpop;
+mod encryption;
+mod endorsement_data;
+mod i18n;
+mod issuer_key;
+mod issuer_profile;
+mod learners;
 mod manager;
 #[cfg(feature = "metrics")]
 mod metrics;
 mod middleware;
+mod notification_data;
+mod oidc;
+mod partners;
+mod presentation_data;
+mod public_verification;
 mod routes;
 mod server;
 mod services;
+mod signed_metadata;
 mod storage;
 mod telemetry;
+mod templates;
+mod verification;
+mod webhooks;
 pub use routes::*;
 
 pub mod prelude {
+    pub use crate::access_token::*;
+    pub use crate::achievements::*;
+    pub use crate::authorization_data::*;
     pub use crate::certificate_data::*;
+    pub use crate::cli::*;
+    pub use crate::clients::*;
+    pub use crate::clr_data::*;
     pub use crate::config::*;
+    pub use crate::credential_schema::*;
+    pub use crate::deferred_data::*;
+    pub use crate::display::*;
+    pub use crate::dpop::*;
+    pub use crate::encryption::*;
+    pub use crate::endorsement_data::*;
+    pub use crate::i18n::*;
+    pub use crate::issuer_key::*;
+    pub use crate::issuer_profile::*;
+    pub use crate::learners::*;
     pub use crate::manager::*;
     #[cfg(feature = "metrics")]
     pub use crate::metrics::*;
     pub use crate::middleware::*;
+    pub use crate::notification_data::*;
+    pub use crate::oidc::*;
+    pub use crate::partners::*;
+    pub use crate::presentation_data::*;
+    pub use crate::public_verification::*;
     pub use crate::routes::*;
     pub use crate::server::*;
     pub use crate::services::*;
+    pub use crate::signed_metadata::*;
     pub use crate::storage::*;
     pub use crate::telemetry::*;
+    pub use crate::templates::*;
+    pub use crate::verification::*;
+    pub use crate::webhooks::*;
 }
@@ -1,12 +1,29 @@
+use clap::Parser;
 use dotenv::dotenv;
 use konnektoren_vc::prelude::*;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     dotenv().ok();
-    init_telemetry()
-        .await
-        .expect("Failed to initialize telemetry.");
+    let cli = Cli::parse();
 
-    start_server().await.expect("Failed to start server.");
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            init_telemetry()
+                .await
+                .expect("Failed to initialize telemetry.");
+
+            start_server().await.expect("Failed to start server.");
+            ExitCode::SUCCESS
+        }
+        command => match run(command).await {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::from(2)
+            }
+        },
+    }
 }
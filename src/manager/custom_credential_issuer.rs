@@ -1,4 +1,5 @@
 use crate::config::Config;
+use crate::display::load_display_config;
 use anyhow::Result;
 use oid4vc_core::Subject;
 use oid4vc_manager::managers::credential_issuer::CredentialIssuerManager;
@@ -7,7 +8,6 @@ use oid4vci::credential_format_profiles::CredentialFormatCollection;
 use oid4vci::credential_issuer::authorization_server_metadata::AuthorizationServerMetadata;
 use oid4vci::credential_issuer::credential_issuer_metadata::CredentialIssuerMetadata;
 use oid4vci::credential_issuer::CredentialIssuer;
-use serde_json::json;
 use std::net::TcpListener;
 use std::sync::Arc;
 use url::Url;
@@ -47,21 +47,12 @@ where
                     authorization_servers: vec![issuer_url.clone()],
                     credential_endpoint: issuer_url.join("/credential")?,
                     batch_credential_endpoint: Some(issuer_url.join("/batch_credential")?),
-                    deferred_credential_endpoint: None,
-                    notification_endpoint: None,
+                    deferred_credential_endpoint: Some(issuer_url.join("/deferred_credential")?),
+                    notification_endpoint: Some(issuer_url.join("/notification")?),
                     credential_response_encryption: None,
                     credential_identifiers_supported: None,
                     signed_metadata: None,
-                    display: Some(vec![json!({
-                        "name": "Konnektoren Credential Issuer",
-                        "locale": "en-US",
-                        "logo": {
-                            "uri": "https://konnektoren.help/favicon.png",
-                            "alt_text": "konnektoren.help square logo"
-                        },
-                        "background_color": "#ff7e00",
-                        "text_color": "#6200ea"
-                    })]),
+                    display: Some(load_display_config()?.issuer_display()),
                     credential_configurations_supported: storage
                         .get_credential_configurations_supported(),
                 },
@@ -70,6 +61,7 @@ where
                     authorization_endpoint: Some(issuer_url.join("/authorize")?),
                     token_endpoint: Some(issuer_url.join("/token")?),
                     pre_authorized_grant_anonymous_access_supported: Some(true),
+                    code_challenge_methods_supported: Some(vec!["S256".to_string()]),
                     ..Default::default()
                 },
             },
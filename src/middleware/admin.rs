@@ -0,0 +1,31 @@
+use crate::config::load_admin_token;
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts, http::StatusCode};
+use axum_auth::AuthBearer;
+use subtle::ConstantTimeEq;
+
+/// Extractor that only succeeds for requests carrying the configured `ADMIN_TOKEN`.
+pub struct AdminAuth;
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AdminAuth {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let expected = load_admin_token().ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                "Admin API is disabled, set ADMIN_TOKEN to enable it".to_string(),
+            )
+        })?;
+
+        let AuthBearer(token) = AuthBearer::from_request_parts(parts, state)
+            .await
+            .map_err(|(status, message)| (status, message.to_string()))?;
+
+        if bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+            Ok(AdminAuth)
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "Invalid admin token".to_string()))
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::dpop::{validate_dpop_proof, DPOP_HEADER};
+use crate::manager::ManagerType;
+use axum::http::{header, request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{async_trait, extract::FromRequestParts, Json};
+use serde_json::json;
+
+/// Access token of a credential request. Tokens bound to a wallet key via DPoP are only
+/// accepted with the `DPoP` authorization scheme and a matching proof of possession.
+pub struct AccessToken(pub String);
+
+fn reject(error: &str, description: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(
+            header::WWW_AUTHENTICATE,
+            format!("DPoP error=\"{}\", Bearer", error),
+        )],
+        Json(json!({ "error": error, "error_description": description })),
+    )
+        .into_response()
+}
+
+#[async_trait]
+impl FromRequestParts<ManagerType> for AccessToken {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        manager: &ManagerType,
+    ) -> Result<Self, Self::Rejection> {
+        let authorization = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| reject("invalid_token", "Missing access token"))?;
+        let (scheme, access_token) = authorization
+            .split_once(' ')
+            .ok_or_else(|| reject("invalid_token", "Malformed authorization header"))?;
+        let access_token = access_token.trim().to_string();
+
+        let bound_jkt = manager.storage.get_access_token_jkt(&access_token);
+        let proof = parts
+            .headers
+            .get(DPOP_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        match (scheme, bound_jkt, proof) {
+            (scheme, None, _) if scheme.eq_ignore_ascii_case("Bearer") => Ok(Self(access_token)),
+            (scheme, Some(bound_jkt), Some(proof)) if scheme.eq_ignore_ascii_case("DPoP") => {
+                let url = manager
+                    .credential_issuer_url()
+                    .and_then(|issuer_url| Ok(issuer_url.join(parts.uri.path())?))
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+                let proof =
+                    validate_dpop_proof(proof, parts.method.as_str(), &url, Some(&access_token))
+                        .map_err(|e| reject("invalid_dpop_proof", &e.to_string()))?;
+                if proof.jkt != bound_jkt {
+                    return Err(reject(
+                        "invalid_dpop_proof",
+                        "DPoP proof is signed with another key than the access token is bound to",
+                    ));
+                }
+                if !manager.storage.record_dpop_proof(&proof.jti) {
+                    return Err(reject("invalid_dpop_proof", "DPoP proof was already used"));
+                }
+                Ok(Self(access_token))
+            }
+            (_, Some(_), _) => Err(reject(
+                "invalid_token",
+                "Access token is bound to a key and requires a DPoP proof",
+            )),
+            _ => Err(reject("invalid_token", "Unsupported authorization scheme")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::dpop::{access_token_hash, jwk_thumbprint};
+    use crate::manager::ConfigurableManager;
+    use crate::storage::MemoryStorage;
+    use aes_gcm::aead::OsRng;
+    use axum::http::Request;
+    use did_key::{generate, Ed25519KeyPair};
+    use jsonwebtoken::jwk::Jwk;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use oid4vc_manager::methods::key_method::KeySubject;
+    use p256::pkcs8::{EncodePrivateKey, LineEnding};
+    use p256::SecretKey;
+    use std::sync::Arc;
+
+    const CREDENTIAL_URL: &str = "http://localhost:3000/credential";
+
+    fn manager() -> ManagerType {
+        let issuer_key = generate::<Ed25519KeyPair>(Some(b"this-is-a-test-issuer-key"));
+        ManagerType::with_config(
+            Some(std::net::TcpListener::bind("127.0.0.1:0").unwrap()),
+            MemoryStorage::new(),
+            Arc::new(KeySubject::from_keypair(issuer_key, None)),
+            Config::new(
+                "this-is-a-test-issuer-key".to_string(),
+                "http://localhost:3000".to_string(),
+            ),
+        )
+        .unwrap()
+    }
+
+    struct WalletKey(SecretKey);
+
+    impl WalletKey {
+        fn new() -> Self {
+            Self(SecretKey::random(&mut OsRng))
+        }
+
+        fn jwk(&self) -> Jwk {
+            serde_json::from_str(&self.0.public_key().to_jwk_string()).unwrap()
+        }
+
+        fn jkt(&self) -> String {
+            jwk_thumbprint(&self.jwk()).unwrap()
+        }
+
+        fn proof(&self, jti: &str, htu: &str, access_token: &str) -> String {
+            let mut header = Header::new(Algorithm::ES256);
+            header.typ = Some("dpop+jwt".to_string());
+            header.jwk = Some(self.jwk());
+            let pem = self.0.to_pkcs8_pem(LineEnding::LF).unwrap();
+            encode(
+                &header,
+                &json!({
+                    "jti": jti,
+                    "htm": "POST",
+                    "htu": htu,
+                    "iat": chrono::Utc::now().timestamp(),
+                    "ath": access_token_hash(access_token),
+                }),
+                &EncodingKey::from_ec_pem(pem.as_bytes()).unwrap(),
+            )
+            .unwrap()
+        }
+    }
+
+    async fn extract(
+        manager: &ManagerType,
+        authorization: &str,
+        proof: Option<String>,
+    ) -> Result<String, StatusCode> {
+        let mut request =
+            Request::post(CREDENTIAL_URL).header(header::AUTHORIZATION, authorization);
+        if let Some(proof) = proof {
+            request = request.header(DPOP_HEADER, proof);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        AccessToken::from_request_parts(&mut parts, manager)
+            .await
+            .map(|AccessToken(access_token)| access_token)
+            .map_err(|response| response.status())
+    }
+
+    #[tokio::test]
+    async fn test_unbound_token_is_accepted_as_bearer() {
+        let manager = manager();
+        manager.storage.associate_access_token(
+            "token".to_string(),
+            vec!["certificate-1".to_string()],
+            None,
+        );
+
+        assert_eq!(
+            extract(&manager, "Bearer token", None).await,
+            Ok("token".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bound_token_requires_proof_of_its_key() {
+        let manager = manager();
+        let wallet_key = WalletKey::new();
+        manager.storage.associate_access_token(
+            "token".to_string(),
+            vec!["certificate-1".to_string()],
+            Some(wallet_key.jkt()),
+        );
+
+        // Without a proof, neither as bearer nor as DPoP token
+        assert_eq!(
+            extract(&manager, "Bearer token", None).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            extract(&manager, "DPoP token", None).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+
+        // With a proof of another key
+        let other_proof = WalletKey::new().proof("jti-1", CREDENTIAL_URL, "token");
+        assert_eq!(
+            extract(&manager, "DPoP token", Some(other_proof)).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+
+        // With a proof for another URL or access token
+        let wrong_htu = wallet_key.proof("jti-2", "http://localhost:3000/notification", "token");
+        assert_eq!(
+            extract(&manager, "DPoP token", Some(wrong_htu)).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        let wrong_ath = wallet_key.proof("jti-3", CREDENTIAL_URL, "other-token");
+        assert_eq!(
+            extract(&manager, "DPoP token", Some(wrong_ath)).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+
+        let proof = wallet_key.proof("jti-4", CREDENTIAL_URL, "token");
+        assert_eq!(
+            extract(&manager, "DPoP token", Some(proof.clone())).await,
+            Ok("token".to_string())
+        );
+        // A proof can only be used once
+        assert_eq!(
+            extract(&manager, "DPoP token", Some(proof)).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::learners::{authenticate_learner, Learner};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts, http::StatusCode};
+use axum_auth::AuthBasic;
+
+/// Extractor for learners signing in with their profile id and password as HTTP basic auth.
+pub struct LearnerAuth(pub Learner);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LearnerAuth {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthBasic((profile_id, password)) =
+            AuthBasic::from_request_parts(parts, state)
+                .await
+                .map_err(|(status, message)| (status, message.to_string()))?;
+
+        authenticate_learner(&profile_id, password.as_deref().unwrap_or_default())
+            .map(LearnerAuth)
+            .ok_or_else(|| {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid profile id or password".to_string(),
+                )
+            })
+    }
+}
@@ -1,2 +1,5 @@
+pub mod admin;
+pub mod dpop;
+pub mod learner;
 #[cfg(feature = "metrics")]
 pub mod metrics;
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Events a wallet can report at the notification endpoint (OID4VCI, section 10).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    CredentialAccepted,
+    CredentialFailure,
+    CredentialDeleted,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationRequest {
+    pub notification_id: String,
+    pub event: NotificationEvent,
+    pub event_description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationErrorResponse {
+    pub error: String,
+}
+
+impl NotificationErrorResponse {
+    pub fn invalid_notification_id() -> Self {
+        Self {
+            error: "invalid_notification_id".to_string(),
+        }
+    }
+
+    pub fn invalid_notification_request() -> Self {
+        Self {
+            error: "invalid_notification_request".to_string(),
+        }
+    }
+}
+
+/// A notification received from a wallet, recorded against the issued certificate.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CredentialNotification {
+    pub notification_id: String,
+    pub event: NotificationEvent,
+    pub event_description: Option<String>,
+    pub received_at: DateTime<Utc>,
+}
@@ -0,0 +1,334 @@
+use crate::config::{load_oidc_config, OidcConfig};
+use anyhow::{anyhow, Result};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tokio::sync::OnceCell;
+use url::Url;
+
+static OIDC_CLIENT: OnceLock<Option<OidcClient>> = OnceLock::new();
+
+/// Loads the OpenID Connect settings once at startup, failing on an incomplete configuration.
+pub fn init_oidc_client() -> Result<()> {
+    let client = load_oidc_config()?.map(OidcClient::new);
+    match &client {
+        Some(client) => log::info!("Learners sign in at {}", client.config.discovery_url),
+        None => log::info!("OIDC_DISCOVERY_URL is not set, federated login is disabled"),
+    }
+    if OIDC_CLIENT.set(client).is_err() {
+        log::warn!("OpenID Connect client already initialized");
+    }
+    Ok(())
+}
+
+/// The configured OpenID Connect provider, `None` if federated login is disabled.
+pub fn oidc_client() -> Option<&'static OidcClient> {
+    OIDC_CLIENT.get()?.as_ref()
+}
+
+/// The subset of the provider's discovery document that is needed for the login.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: Url,
+    pub token_endpoint: Url,
+    pub jwks_uri: Url,
+    /// Algorithms the provider signs ID tokens with, `RS256` if not announced.
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+}
+
+impl ProviderMetadata {
+    /// The announced ID token algorithms this client can validate.
+    fn id_token_algorithms(&self) -> Vec<Algorithm> {
+        if self.id_token_signing_alg_values_supported.is_empty() {
+            return vec![Algorithm::RS256];
+        }
+        self.id_token_signing_alg_values_supported
+            .iter()
+            .filter_map(|alg| Algorithm::from_str(alg).ok())
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ProviderTokenResponse {
+    id_token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+/// The learner's identity as asserted by the OpenID Connect provider.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FederatedIdentity {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+/// Relying party client for the configured OpenID Connect provider.
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>, // discovery document, fetched on first use
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+        }
+    }
+
+    /// Fetches the provider's discovery document once and keeps it, failed attempts are retried.
+    pub async fn discover(&self) -> Result<&ProviderMetadata> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let metadata: ProviderMetadata = self
+                    .http
+                    .get(&self.config.discovery_url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                Ok::<_, anyhow::Error>(metadata)
+            })
+            .await
+    }
+
+    /// Builds the URL the learner is redirected to for signing in at the provider.
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<Url> {
+        let metadata = self.discover().await?;
+        let mut url = metadata.authorization_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(url)
+    }
+
+    /// Exchanges the provider's authorization code and validates the returned ID token.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<FederatedIdentity> {
+        let metadata = self.discover().await?;
+        let token_response: ProviderTokenResponse = self
+            .http
+            .post(metadata.token_endpoint.clone())
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_uri.as_str()),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let claims = self
+            .validate_id_token(metadata, &token_response.id_token)
+            .await?;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(anyhow!("ID token nonce does not match"));
+        }
+
+        Ok(FederatedIdentity {
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified,
+        })
+    }
+
+    async fn validate_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+    ) -> Result<IdTokenClaims> {
+        let header = decode_header(id_token)?;
+        // The algorithm of the token header is only trusted if the provider announced it
+        if !metadata.id_token_algorithms().contains(&header.alg) {
+            return Err(anyhow!(
+                "ID token is signed with {:?}, which the provider does not announce",
+                header.alg
+            ));
+        }
+        let key = match header.alg {
+            // Symmetrically signed ID tokens use the client secret as key (OIDC Core, section 10.1)
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                DecodingKey::from_secret(self.config.client_secret.as_bytes())
+            }
+            _ => {
+                let jwks: JwkSet = self
+                    .http
+                    .get(metadata.jwks_uri.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let jwk = match &header.kid {
+                    Some(kid) => jwks.find(kid),
+                    None => jwks.keys.first(),
+                }
+                .ok_or_else(|| anyhow!("No matching key in the provider's JWKS"))?;
+                DecodingKey::from_jwk(jwk)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+        Ok(decode::<IdTokenClaims>(id_token, &key, &validation)?.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::get, routing::post, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const CLIENT_SECRET: &str = "mock-client-secret";
+
+    #[derive(Clone)]
+    struct MockProvider {
+        issuer: String,
+        algorithms: Vec<&'static str>,
+        discoveries: Arc<AtomicUsize>,
+    }
+
+    async fn openid_configuration(State(provider): State<MockProvider>) -> Json<Value> {
+        provider.discoveries.fetch_add(1, Ordering::SeqCst);
+        let issuer = &provider.issuer;
+        Json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+            "id_token_signing_alg_values_supported": provider.algorithms,
+        }))
+    }
+
+    async fn token(State(provider): State<MockProvider>) -> Json<Value> {
+        let claims = json!({
+            "iss": provider.issuer,
+            "aud": "konnektoren-vc",
+            "sub": "learner-1",
+            "email": "alice@example.com",
+            "email_verified": true,
+            "nonce": "expected-nonce",
+            "exp": chrono::Utc::now().timestamp() + 300,
+        });
+        let id_token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+        )
+        .unwrap();
+        Json(json!({ "access_token": "mock", "token_type": "Bearer", "id_token": id_token }))
+    }
+
+    /// Starts a provider that signs its ID tokens with HS256 and announces `algorithms`.
+    fn mock_provider(algorithms: Vec<&'static str>) -> (OidcClient, MockProvider) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let provider = MockProvider {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            algorithms,
+            discoveries: Arc::new(AtomicUsize::new(0)),
+        };
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(openid_configuration),
+            )
+            .route("/token", post(token))
+            .with_state(provider.clone());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let client = OidcClient::new(OidcConfig {
+            discovery_url: format!("{}/.well-known/openid-configuration", provider.issuer),
+            client_id: "konnektoren-vc".to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            redirect_uri: "http://localhost:3000/authorize/callback".to_string(),
+            scopes: "openid email".to_string(),
+        });
+        (client, provider)
+    }
+
+    #[tokio::test]
+    async fn test_login_with_mock_provider() {
+        let (client, provider) = mock_provider(vec!["HS256"]);
+
+        let url = client
+            .authorization_url("session", "expected-nonce", "challenge")
+            .await
+            .unwrap();
+        assert!(url
+            .as_str()
+            .starts_with(&format!("{}/authorize", provider.issuer)));
+        assert!(url
+            .query_pairs()
+            .any(|(key, value)| key == "client_id" && value == "konnektoren-vc"));
+
+        let identity = client
+            .exchange_code("code", "verifier", "expected-nonce")
+            .await
+            .unwrap();
+        assert_eq!(identity.subject, "learner-1");
+        assert_eq!(identity.email.as_deref(), Some("alice@example.com"));
+        assert!(identity.email_verified);
+
+        assert!(client
+            .exchange_code("code", "verifier", "other-nonce")
+            .await
+            .is_err());
+
+        // The discovery document is only fetched once
+        assert_eq!(provider.discoveries.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_id_token_algorithm_is_pinned() {
+        let (client, _) = mock_provider(vec!["RS256"]);
+
+        assert!(client
+            .exchange_code("code", "verifier", "expected-nonce")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use crate::issuer_key::ed25519_key_from_secret;
+use anyhow::Result;
+use did_key::{DIDCore, PatchedKeyPair};
+use serde::{Deserialize, Serialize};
+use std::{env, fs::File};
+
+/// A partner language school that endorses Konnektoren achievements.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Partner {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// `did:key` the partner signs their endorsements with.
+    #[serde(default)]
+    pub did: Option<String>,
+    /// Secret of the partner's key, if the issuer signs endorsements on their behalf.
+    #[serde(default, skip_serializing)]
+    pub private_key: Option<String>,
+}
+
+impl Partner {
+    /// Key the issuer signs endorsements with on behalf of the partner.
+    pub fn signing_key(&self) -> Option<PatchedKeyPair> {
+        self.private_key.as_deref().map(ed25519_key_from_secret)
+    }
+
+    /// The partner's DID, derived from their key if the issuer holds it.
+    pub fn did(&self) -> Option<String> {
+        self.signing_key()
+            .map(|key| key.get_did_document(did_key::Config::default()).id)
+            .or_else(|| self.did.clone())
+    }
+}
+
+/// Loads the partners from the JSON file referenced by `PARTNERS_FILE`.
+pub fn load_partners() -> Result<Vec<Partner>> {
+    match env::var("PARTNERS_FILE") {
+        Ok(path) => Ok(serde_json::from_reader(File::open(path)?)?),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+pub fn find_partner(partner_id: &str) -> Option<Partner> {
+    load_partners()
+        .map_err(|e| log::error!("Failed to load partners: {}", e))
+        .ok()?
+        .into_iter()
+        .find(|partner| partner.id == partner_id)
+}
@@ -0,0 +1,206 @@
+use crate::verification::VerificationReport;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Lifetime of a presentation request in minutes, the learner has to answer it in time.
+pub const PRESENTATION_REQUEST_TTL_MINUTES: i64 = 10;
+
+/// Id of the input descriptor or DCQL credential query of the Konnektoren certificate.
+pub const KONNEKTOREN_CERTIFICATE_QUERY_ID: &str = "konnektoren_certificate";
+
+/// Audience of request objects for wallets acting as self-issued OpenID provider.
+pub const SELF_ISSUED_AUDIENCE: &str = "https://self-issued.me/v2";
+
+/// Query language the relying party asks for credentials with.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryLanguage {
+    #[default]
+    PresentationDefinition,
+    Dcql,
+}
+
+/// Request of a partner school to see proof of a learner's Konnektoren level.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PresentationRequestCreate {
+    /// Minimum performance in percent the certificate has to show.
+    #[serde(default)]
+    pub min_performance: u8,
+    #[serde(default)]
+    pub query_language: QueryLanguage,
+    #[serde(default)]
+    pub partner_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentationStatus {
+    Pending,
+    Verified,
+    Rejected,
+}
+
+/// What the wallet presented, and why it was accepted or rejected.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PresentationResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_path_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub performance_percentage: Option<u8>,
+    pub credentials: Vec<VerificationReport>,
+    pub errors: Vec<String>,
+}
+
+/// A presentation request, identified by the `state` the wallet sends back.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PresentationSession {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub nonce: String,
+    pub min_performance: u8,
+    pub query_language: QueryLanguage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partner_id: Option<String>,
+    pub status: PresentationStatus,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<PresentationResult>,
+}
+
+/// Authorization response of the wallet with `response_mode=direct_post`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PresentationResponse {
+    #[serde(default)]
+    pub vp_token: Option<String>,
+    #[serde(default)]
+    pub presentation_submission: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_description: Option<String>,
+}
+
+/// Returned to the relying party after a presentation request was created.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PresentationRequestCreated {
+    pub id: String,
+    /// `openid4vp://` URL the learner's wallet opens.
+    pub request_url: String,
+    pub qr_code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Presentation definition (DIF Presentation Exchange 2.0) of a Konnektoren certificate.
+pub fn presentation_definition(session: &PresentationSession, issuer_did: &str) -> Value {
+    json!({
+        "id": session.id,
+        "name": "Konnektoren certificate",
+        "purpose": format!(
+            "Proof of a Konnektoren certificate with a performance of at least {}%",
+            session.min_performance
+        ),
+        "input_descriptors": [{
+            "id": KONNEKTOREN_CERTIFICATE_QUERY_ID,
+            "format": { "jwt_vc_json": { "alg": ["EdDSA"] } },
+            "constraints": {
+                "fields": [
+                    {
+                        "path": ["$.vc.type"],
+                        "filter": { "type": "array", "contains": { "const": "OpenBadgeCredential" } }
+                    },
+                    {
+                        "path": ["$.iss", "$.vc.issuer.otherIdentifier[0].identifier"],
+                        "filter": { "type": "string", "const": issuer_did }
+                    },
+                    { "path": ["$.vc.credentialSubject.result[0].value"] }
+                ]
+            }
+        }]
+    })
+}
+
+/// DCQL query of a Konnektoren certificate.
+pub fn dcql_query() -> Value {
+    json!({
+        "credentials": [{
+            "id": KONNEKTOREN_CERTIFICATE_QUERY_ID,
+            "format": "jwt_vc_json",
+            "meta": { "type_values": [["OpenBadgeCredential"]] },
+            "claims": [
+                { "path": ["credentialSubject", "achievement", "name"] },
+                { "path": ["credentialSubject", "result"] }
+            ]
+        }]
+    })
+}
+
+/// Claims of the request object the wallet fetches from the `request_uri`.
+pub fn authorization_request(
+    session: &PresentationSession,
+    client_id: &str,
+    response_uri: &str,
+) -> Value {
+    let mut request = json!({
+        "iss": client_id,
+        "aud": SELF_ISSUED_AUDIENCE,
+        "iat": session.created_at.timestamp(),
+        "exp": session.expires_at.timestamp(),
+        "client_id": client_id,
+        "client_id_scheme": "did",
+        "response_type": "vp_token",
+        "response_mode": "direct_post",
+        "response_uri": response_uri,
+        "nonce": session.nonce,
+        "state": session.id,
+    });
+    match session.query_language {
+        QueryLanguage::PresentationDefinition => {
+            request["presentation_definition"] = presentation_definition(session, client_id)
+        }
+        QueryLanguage::Dcql => request["dcql_query"] = dcql_query(),
+    }
+    request
+}
+
+/// Overall performance in percent stated by a Konnektoren certificate.
+pub fn performance_of(credential: &Value) -> Option<u8> {
+    let results = credential["credentialSubject"]["result"].as_array()?;
+    let score = results
+        .iter()
+        .find(|result| {
+            result["resultDescription"]
+                .as_str()
+                .map_or(false, |id| id.ends_with("#result-score"))
+        })
+        .or_else(|| results.first())?;
+    score["value"].as_str()?.trim_end_matches('%').parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_performance_of() {
+        let credential = json!({
+            "credentialSubject": {
+                "result": [
+                    { "type": ["Result"], "resultDescription": "https://a/b#result-c1", "value": "40" },
+                    { "type": ["Result"], "resultDescription": "https://a/b#result-score", "value": "85" }
+                ]
+            }
+        });
+        assert_eq!(performance_of(&credential), Some(85));
+
+        let legacy =
+            json!({ "credentialSubject": { "result": [{ "type": "Result", "value": "80%" }] } });
+        assert_eq!(performance_of(&legacy), Some(80));
+        assert_eq!(performance_of(&json!({})), None);
+    }
+}
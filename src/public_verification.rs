@@ -0,0 +1,165 @@
+use crate::certificate_data::CertificateData;
+use crate::config::load_issuer_url;
+use crate::issuer_profile::{issuer_profile_url, ISSUER_PROFILE_NAME};
+use crate::storage::get_issuer_did;
+use crate::templates::render;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const VERIFY_TEMPLATE: &str = include_str!("../assets/templates/verify.html");
+
+/// Public verification page of a credential, also used as the credential's `id`.
+pub fn verification_url(credential_id: &str) -> String {
+    format!(
+        "{}/verify/{}",
+        load_issuer_url().trim_end_matches('/'),
+        credential_id
+    )
+}
+
+/// Public id of a credential issued by this issuer, the last segment of its verification URL.
+pub fn public_credential_id(credential: &Value) -> Option<String> {
+    credential["id"]
+        .as_str()?
+        .strip_prefix(&verification_url(""))
+        .filter(|credential_id| !credential_id.is_empty())
+        .map(str::to_string)
+}
+
+/// An issued credential that can be looked up on its verification page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PublicCredential {
+    pub certificate_id: String,
+    pub issued_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicCredentialStatus {
+    Active,
+    Revoked,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PublicIssuer {
+    pub id: String,
+    pub name: String,
+    pub did: String,
+}
+
+/// What anyone holding the link of a credential may see about it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PublicVerification {
+    pub credential_id: String,
+    pub verification_url: String,
+    pub status: PublicCredentialStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub issued_at: DateTime<Utc>,
+    pub issuer: PublicIssuer,
+    pub learner_name: String,
+    pub achievement_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub achievement_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub performance_percentage: u8,
+    pub solved_challenges: usize,
+    pub total_challenges: usize,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Learner's choice whether their credentials can be looked up on the verification page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VisibilityRequest {
+    pub public: bool,
+}
+
+impl PublicVerification {
+    pub fn new(
+        credential_id: &str,
+        credential: &PublicCredential,
+        certificate: &CertificateData,
+        revoked_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        let achievement = certificate.achievement();
+        Self {
+            credential_id: credential_id.to_string(),
+            verification_url: verification_url(credential_id),
+            status: match revoked_at {
+                Some(_) => PublicCredentialStatus::Revoked,
+                None => PublicCredentialStatus::Active,
+            },
+            revoked_at,
+            issued_at: credential.issued_at,
+            issuer: PublicIssuer {
+                id: issuer_profile_url(),
+                name: ISSUER_PROFILE_NAME.to_string(),
+                did: get_issuer_did(),
+            },
+            learner_name: certificate.profile_name.clone(),
+            achievement_name: certificate.game_path_name.clone(),
+            achievement_id: achievement.map(|achievement| achievement.url()),
+            level: achievement.and_then(|achievement| achievement.level.clone()),
+            performance_percentage: certificate.performance_percentage,
+            solved_challenges: certificate.solved_challenges,
+            total_challenges: certificate.total_challenges,
+            completed_at: certificate.date,
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let (status_class, status) = match (self.status, self.revoked_at) {
+            (PublicCredentialStatus::Revoked, Some(revoked_at)) => (
+                "revoked",
+                format!("Revoked on {}", revoked_at.format("%Y-%m-%d")),
+            ),
+            _ => ("active", "Valid, not revoked".to_string()),
+        };
+        let challenges = format!(
+            "{} of {} ({}%)",
+            self.solved_challenges, self.total_challenges, self.performance_percentage
+        );
+        let completed_at = self.completed_at.format("%Y-%m-%d").to_string();
+        let issued_at = self.issued_at.format("%Y-%m-%d").to_string();
+        render(
+            VERIFY_TEMPLATE,
+            &[
+                ("credential_id", self.credential_id.as_str()),
+                ("status_class", status_class),
+                ("status", status.as_str()),
+                ("learner_name", self.learner_name.as_str()),
+                ("achievement_name", self.achievement_name.as_str()),
+                ("level", self.level.as_deref().unwrap_or("-")),
+                ("challenges", challenges.as_str()),
+                ("completed_at", completed_at.as_str()),
+                ("issued_at", issued_at.as_str()),
+                ("issuer_name", self.issuer.name.as_str()),
+                ("issuer_id", self.issuer.id.as_str()),
+                ("issuer_did", self.issuer.did.as_str()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_public_credential_id() {
+        crate::config::set_test_env();
+        let credential = json!({ "id": verification_url("4f1c7a52") });
+        assert_eq!(
+            public_credential_id(&credential),
+            Some("4f1c7a52".to_string())
+        );
+        assert_eq!(
+            public_credential_id(&json!({ "id": "urn:uuid:4f1c7a52" })),
+            None
+        );
+        assert_eq!(public_credential_id(&json!({})), None);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::deferred_data::{PendingReview, ReviewDecision, ReviewStatus};
+use crate::endorsement_data::{
+    is_endorsable, sign_endorsement, verify_endorsement_jwt, Endorsement, EndorsementRequest,
+};
+use crate::manager::ManagerType;
+use crate::middleware::admin::AdminAuth;
+use crate::notification_data::CredentialNotification;
+use crate::partners::find_partner;
+use crate::presentation_data::{
+    PresentationRequestCreate, PresentationRequestCreated, PresentationSession,
+};
+use crate::services::{CertificateService, PresentationService};
+use crate::webhooks::{DeliveryFilter, WebhookDelivery};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct ReviewFilter {
+    pub status: Option<ReviewStatus>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EndorsementFilter {
+    pub target_id: Option<String>,
+}
+
+pub async fn list_webhook_deliveries(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Query(filter): Query<DeliveryFilter>,
+) -> Json<Vec<WebhookDelivery>> {
+    let deliveries = manager
+        .storage
+        .webhooks()
+        .map(|webhooks| webhooks.deliveries(&filter))
+        .unwrap_or_default();
+    Json(deliveries)
+}
+
+pub async fn revoke_certificate(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(certificate_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if manager.storage.revoke_certificate(&certificate_id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Certificate {} not found", certificate_id),
+        ))
+    }
+}
+
+/// Creates an offer of a Comprehensive Learner Record bundling all certificates of a learner.
+pub async fn create_clr_offer(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(profile_id): Path<String>,
+) -> Result<Json<String>, (StatusCode, String)> {
+    CertificateService::new(&manager)
+        .generate_clr_offer_url(&profile_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))
+}
+
+/// Records a partner's endorsement, signed by the partner or on their behalf.
+pub async fn create_endorsement(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Json(request): Json<EndorsementRequest>,
+) -> Result<(StatusCode, Json<Endorsement>), (StatusCode, String)> {
+    let partner = find_partner(&request.partner_id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Partner {} not found", request.partner_id),
+        )
+    })?;
+    let endorsement = match (&request.endorsement_jwt, &request.target_id) {
+        (Some(endorsement_jwt), _) => verify_endorsement_jwt(&partner, endorsement_jwt),
+        (None, Some(target_id)) if is_endorsable(target_id) => sign_endorsement(
+            &partner,
+            target_id,
+            request.comment.as_deref().unwrap_or_default(),
+        ),
+        (None, Some(target_id)) => Err(anyhow::anyhow!("Unknown endorsement target {}", target_id)),
+        (None, None) => Err(anyhow::anyhow!(
+            "Either endorsement_jwt or target_id is required"
+        )),
+    }
+    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    manager.storage.store_endorsement(endorsement.clone());
+    Ok((StatusCode::CREATED, Json(endorsement)))
+}
+
+pub async fn list_endorsements(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Query(filter): Query<EndorsementFilter>,
+) -> Json<Vec<Endorsement>> {
+    Json(
+        manager
+            .storage
+            .get_endorsements(filter.target_id.as_deref()),
+    )
+}
+
+/// Creates an OpenID4VP request for a Konnektoren certificate, shown to the learner as QR code.
+pub async fn create_presentation_request(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Json(request): Json<PresentationRequestCreate>,
+) -> Result<(StatusCode, Json<PresentationRequestCreated>), (StatusCode, String)> {
+    PresentationService::new(&manager)
+        .create_request(request)
+        .map(|created| (StatusCode::CREATED, Json(created)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Status and verification result of a presentation request.
+pub async fn get_presentation(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(presentation_id): Path<String>,
+) -> Result<Json<PresentationSession>, (StatusCode, String)> {
+    PresentationService::new(&manager)
+        .get_session(&presentation_id)
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Presentation {} not found", presentation_id),
+            )
+        })
+}
+
+pub async fn list_certificate_notifications(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(certificate_id): Path<String>,
+) -> Result<Json<Vec<CredentialNotification>>, (StatusCode, String)> {
+    match manager.storage.get_certificate(&certificate_id) {
+        Some(_) => Ok(Json(manager.storage.get_notifications(&certificate_id))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("Certificate {} not found", certificate_id),
+        )),
+    }
+}
+
+pub async fn list_reviews(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Query(filter): Query<ReviewFilter>,
+) -> Json<Vec<PendingReview>> {
+    Json(manager.storage.get_reviews(filter.status))
+}
+
+pub async fn approve_review(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(transaction_id): Path<String>,
+    decision: Option<Json<ReviewDecision>>,
+) -> Result<Json<PendingReview>, (StatusCode, String)> {
+    decide_review(manager, transaction_id, ReviewStatus::Approved, decision)
+}
+
+pub async fn reject_review(
+    _admin: AdminAuth,
+    State(manager): State<ManagerType>,
+    Path(transaction_id): Path<String>,
+    decision: Option<Json<ReviewDecision>>,
+) -> Result<Json<PendingReview>, (StatusCode, String)> {
+    decide_review(manager, transaction_id, ReviewStatus::Rejected, decision)
+}
+
+fn decide_review(
+    manager: ManagerType,
+    transaction_id: String,
+    status: ReviewStatus,
+    decision: Option<Json<ReviewDecision>>,
+) -> Result<Json<PendingReview>, (StatusCode, String)> {
+    let Json(decision) = decision.unwrap_or_default();
+    manager
+        .storage
+        .decide_review(&transaction_id, status, decision.reason)
+        .map(Json)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("No pending review with transaction id {}", transaction_id),
+            )
+        })
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /api/v1/admin");
+    Router::new()
+        .route("/webhooks/deliveries", get(list_webhook_deliveries))
+        .route(
+            "/certificates/:certificate_id/revoke",
+            post(revoke_certificate),
+        )
+        .route(
+            "/certificates/:certificate_id/notifications",
+            get(list_certificate_notifications),
+        )
+        .route("/learners/:profile_id/clr/offer", post(create_clr_offer))
+        .route(
+            "/endorsements",
+            get(list_endorsements).post(create_endorsement),
+        )
+        .route("/presentations", post(create_presentation_request))
+        .route("/presentations/:presentation_id", get(get_presentation))
+        .route("/reviews", get(list_reviews))
+        .route("/reviews/:transaction_id/approve", post(approve_review))
+        .route("/reviews/:transaction_id/reject", post(reject_review))
+}
@@ -0,0 +1,287 @@
+use crate::authorization_data::{
+    pkce_challenge, AuthorizationRequestQuery, AuthorizationSession, FederatedCallbackQuery,
+    FederatedLogin, LoginForm,
+};
+use crate::clients::find_client;
+use crate::learners::{authenticate_learner, find_federated_learner, Learner};
+use crate::manager::ManagerType;
+use crate::oidc::oidc_client;
+use crate::templates::render;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Form, Router};
+use oid4vc_core::generate_authorization_code;
+use url::Url;
+
+const AUTHORIZE_TEMPLATE: &str = include_str!("../../assets/templates/authorize.html");
+
+fn login_page(status: StatusCode, session: &AuthorizationSession, error: &str) -> Response {
+    let page = render(
+        AUTHORIZE_TEMPLATE,
+        &[
+            ("client_id", session.client_id.as_str()),
+            ("session_id", session.id.as_str()),
+            ("error", error),
+        ],
+    );
+    (status, Html(page)).into_response()
+}
+
+fn redirect_to_wallet(
+    redirect_uri: &str,
+    state: Option<&str>,
+    params: &[(&str, &str)],
+) -> Response {
+    match Url::parse(redirect_uri) {
+        Ok(mut url) => {
+            url.query_pairs_mut().extend_pairs(params);
+            if let Some(state) = state {
+                url.query_pairs_mut().append_pair("state", state);
+            }
+            Redirect::to(url.as_str()).into_response()
+        }
+        Err(_) => (StatusCode::BAD_REQUEST, "Invalid redirect_uri").into_response(),
+    }
+}
+
+/// Authorization endpoint, renders the learner login and consent page.
+#[tracing::instrument(skip_all)]
+pub async fn authorize(
+    State(manager): State<ManagerType>,
+    Query(request): Query<AuthorizationRequestQuery>,
+) -> Response {
+    // Errors are only redirected to URIs registered for the wallet, everything else is answered here
+    let client = match find_client(&request.client_id) {
+        Some(client) => client,
+        None => {
+            log::warn!("Unknown client {}", request.client_id);
+            return (StatusCode::BAD_REQUEST, "Unknown client_id").into_response();
+        }
+    };
+    if !client.allows_redirect_uri(&request.redirect_uri) {
+        log::warn!(
+            "Redirect URI {} is not registered for client {}",
+            request.redirect_uri,
+            client.client_id
+        );
+        return (StatusCode::BAD_REQUEST, "Invalid redirect_uri").into_response();
+    }
+
+    let state = request.state.clone();
+    if request.response_type != "code" {
+        return redirect_to_wallet(
+            &request.redirect_uri,
+            state.as_deref(),
+            &[("error", "unsupported_response_type")],
+        );
+    }
+
+    // PKCE with S256 is mandatory for all wallets
+    let code_challenge = match (
+        &request.code_challenge,
+        request.code_challenge_method.as_deref(),
+    ) {
+        (Some(code_challenge), Some("S256")) => code_challenge.clone(),
+        _ => {
+            return redirect_to_wallet(
+                &request.redirect_uri,
+                state.as_deref(),
+                &[
+                    ("error", "invalid_request"),
+                    ("error_description", "PKCE with S256 is required"),
+                ],
+            )
+        }
+    };
+
+    let session = manager
+        .storage
+        .start_authorization_session(request, code_challenge);
+
+    // Delegate the learner login to the OpenID Connect provider if one is configured
+    if let Some(oidc_client) = oidc_client() {
+        let federated_login = FederatedLogin {
+            nonce: generate_authorization_code(32),
+            code_verifier: generate_authorization_code(64),
+        };
+        let authorization_url = oidc_client
+            .authorization_url(
+                &session.id,
+                &federated_login.nonce,
+                &pkce_challenge(&federated_login.code_verifier),
+            )
+            .await;
+        return match authorization_url {
+            Ok(authorization_url) => {
+                manager
+                    .storage
+                    .set_federated_login(&session.id, federated_login);
+                Redirect::to(authorization_url.as_str()).into_response()
+            }
+            Err(e) => {
+                log::error!("OpenID Connect provider unavailable: {}", e);
+                login_page(
+                    StatusCode::BAD_GATEWAY,
+                    &session,
+                    "The login provider is currently unavailable.",
+                )
+            }
+        };
+    }
+
+    login_page(StatusCode::OK, &session, "")
+}
+
+/// Callback of the OpenID Connect provider after the learner signed in there.
+#[tracing::instrument(skip_all)]
+pub async fn federated_callback(
+    State(manager): State<ManagerType>,
+    Query(callback): Query<FederatedCallbackQuery>,
+) -> Response {
+    let session = match manager.storage.get_authorization_session(&callback.state) {
+        Some(session) => session,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Authorization session expired, please restart from your wallet",
+            )
+                .into_response()
+        }
+    };
+    let state = session.state.as_deref();
+
+    let (oidc_client, federated_login, code) = match (
+        oidc_client(),
+        session.federated_login.clone(),
+        callback.code.as_deref(),
+    ) {
+        (Some(oidc_client), Some(federated_login), Some(code)) => {
+            (oidc_client, federated_login, code)
+        }
+        _ => {
+            log::warn!("Federated login failed: {:?}", callback.error);
+            manager.storage.cancel_authorization_session(&session.id);
+            return redirect_to_wallet(&session.redirect_uri, state, &[("error", "access_denied")]);
+        }
+    };
+
+    let identity = match oidc_client
+        .exchange_code(code, &federated_login.code_verifier, &federated_login.nonce)
+        .await
+    {
+        Ok(identity) => identity,
+        Err(e) => {
+            log::error!("Failed to validate the federated login: {}", e);
+            manager.storage.cancel_authorization_session(&session.id);
+            return redirect_to_wallet(&session.redirect_uri, state, &[("error", "access_denied")]);
+        }
+    };
+
+    match find_federated_learner(&identity) {
+        Some(learner) => complete_login(&manager, &session, &learner),
+        None => {
+            log::warn!(
+                "No Konnektoren profile for subject {} ({:?})",
+                identity.subject,
+                identity.email
+            );
+            manager.storage.cancel_authorization_session(&session.id);
+            redirect_to_wallet(&session.redirect_uri, state, &[("error", "access_denied")])
+        }
+    }
+}
+
+/// Handles the submitted login form.
+#[tracing::instrument(skip_all)]
+pub async fn login(State(manager): State<ManagerType>, Form(form): Form<LoginForm>) -> Response {
+    let session = match manager.storage.get_authorization_session(&form.session_id) {
+        Some(session) => session,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Authorization session expired, please restart from your wallet",
+            )
+                .into_response()
+        }
+    };
+    let state = session.state.as_deref();
+
+    if form.consent.as_deref() != Some("approve") {
+        manager.storage.cancel_authorization_session(&session.id);
+        return redirect_to_wallet(&session.redirect_uri, state, &[("error", "access_denied")]);
+    }
+
+    let learner = match (form.profile_id.as_deref(), form.password.as_deref()) {
+        (Some(profile_id), Some(password)) => authenticate_learner(profile_id, password),
+        _ => None,
+    };
+    match learner {
+        Some(learner) => complete_login(&manager, &session, &learner),
+        None => login_page(
+            StatusCode::UNAUTHORIZED,
+            &session,
+            "Unknown profile or wrong password.",
+        ),
+    }
+}
+
+/// Binds the signed in learner to their certificates and redirects back to the wallet with an authorization code.
+fn complete_login(
+    manager: &ManagerType,
+    session: &AuthorizationSession,
+    learner: &Learner,
+) -> Response {
+    let state = session.state.as_deref();
+
+    // Offers pin the certificates via the issuer state, wallet initiated flows get all of the learner's certificates
+    let certificate_ids: Vec<String> = match &session.issuer_state {
+        Some(issuer_state) => manager
+            .storage
+            .get_certificate_ids_by_issuer_state(issuer_state)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|certificate_id| {
+                !manager.storage.is_revoked(certificate_id)
+                    && manager.storage.get_certificate(certificate_id).map_or(
+                        false,
+                        |certificate| {
+                            certificate.profile_id.as_deref() == Some(learner.profile_id.as_str())
+                        },
+                    )
+            })
+            .collect(),
+        None => manager
+            .storage
+            .find_certificate_ids_by_profile(&learner.profile_id),
+    };
+
+    if certificate_ids.is_empty() {
+        return login_page(
+            StatusCode::NOT_FOUND,
+            session,
+            "There are no certificates for this profile.",
+        );
+    }
+
+    match manager.storage.issue_authorization_code(
+        &session.id,
+        &learner.profile_id,
+        certificate_ids,
+    ) {
+        Some(code) => redirect_to_wallet(&session.redirect_uri, state, &[("code", code.as_str())]),
+        None => (
+            StatusCode::BAD_REQUEST,
+            "Authorization session expired, please restart from your wallet",
+        )
+            .into_response(),
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /authorize");
+    Router::new()
+        .route("/authorize", get(authorize).post(login))
+        .route("/authorize/callback", get(federated_callback))
+}
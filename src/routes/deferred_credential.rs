@@ -0,0 +1,48 @@
+use crate::deferred_data::{DeferredCredentialErrorResponse, DeferredCredentialRequest};
+use crate::manager::ManagerType;
+use crate::middleware::dpop::AccessToken;
+use crate::oid4vci::{credential_response, response_encryption};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::Value;
+
+/// Deferred credential endpoint, polled by wallets until a teacher reviewed the certificate.
+/// The credential is encrypted like at the credential endpoint if the wallet asks for it.
+#[tracing::instrument(skip_all)]
+pub async fn post_deferred_credential(
+    State(manager): State<ManagerType>,
+    AccessToken(access_token): AccessToken,
+    Json(request): Json<Value>,
+) -> Response {
+    let encryption = match response_encryption(&request) {
+        Ok(encryption) => encryption,
+        Err(response) => return response,
+    };
+    let request: DeferredCredentialRequest = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(DeferredCredentialErrorResponse::invalid_transaction_id()),
+            )
+                .into_response()
+        }
+    };
+
+    match manager.storage.get_deferred_credential_response(
+        &access_token,
+        &request.transaction_id,
+        manager.credential_issuer.subject.clone(),
+    ) {
+        Ok(credential) => credential_response(Some(credential), encryption.as_ref()),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /deferred_credential");
+    Router::new().route("/deferred_credential", post(post_deferred_credential))
+}
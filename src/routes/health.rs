@@ -104,11 +104,18 @@ async fn test_storage(
     let test_id = Uuid::new_v4().to_string();
     let test_certificate = CertificateData {
         game_path_name: "health_check".to_string(),
+        game_path_id: None,
         total_challenges: 1,
         solved_challenges: 1,
         performance_percentage: 100,
         profile_name: "health_check".to_string(),
+        profile_id: None,
         date: Utc::now(),
+        requires_review: false,
+        locale: None,
+        pass_threshold: None,
+        challenge_results: vec![],
+        summary_url: None,
     };
 
     // Test store and retrieve
@@ -123,10 +130,10 @@ async fn test_storage(
 }
 
 async fn test_did_generation() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    use crate::config::load_config;
+    use crate::config::load_private_key;
     use did_key::{generate, DIDCore, Ed25519KeyPair, PatchedKeyPair};
 
-    let (priv_key, _) = load_config();
+    let priv_key = load_private_key();
     let issuer_key: PatchedKeyPair = generate::<Ed25519KeyPair>(Some(priv_key.as_bytes()));
     let _document = issuer_key.get_did_document(did_key::Config::default());
 
@@ -1,7 +1,15 @@
+pub mod admin;
 pub mod assets;
+pub mod authorize;
+pub mod deferred_credential;
 mod example;
 pub mod health;
+pub mod notification;
+pub mod ob3;
+pub mod oid4vci;
+pub mod oid4vp;
 pub mod v1;
+pub mod verify;
 pub mod well_known;
 
 pub use example::*;
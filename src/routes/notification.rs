@@ -0,0 +1,37 @@
+use crate::manager::ManagerType;
+use crate::middleware::dpop::AccessToken;
+use crate::notification_data::{NotificationErrorResponse, NotificationRequest};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+
+/// Notification endpoint, called by wallets after they processed an issued credential.
+#[tracing::instrument(skip_all)]
+pub async fn post_notification(
+    State(manager): State<ManagerType>,
+    AccessToken(access_token): AccessToken,
+    request: Result<Json<NotificationRequest>, JsonRejection>,
+) -> Result<StatusCode, (StatusCode, Json<NotificationErrorResponse>)> {
+    let Json(request) = request.map_err(|e| {
+        log::warn!("Invalid notification request: {}", e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(NotificationErrorResponse::invalid_notification_request()),
+        )
+    })?;
+
+    match manager.storage.record_notification(&access_token, request) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            Json(NotificationErrorResponse::invalid_notification_id()),
+        )),
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /notification");
+    Router::new().route("/notification", post(post_notification))
+}
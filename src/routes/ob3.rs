@@ -0,0 +1,104 @@
+use crate::achievements::achievement_definitions;
+use crate::i18n::languages;
+use crate::issuer_profile::{issuer_profile, ISSUER_PROFILE_SLUG};
+use crate::manager::ManagerType;
+use axum::extract::Path;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+/// First language of the `Accept-Language` header, e.g. `de-DE` for `de-DE,de;q=0.9`.
+fn preferred_locale(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|locale| {
+            locale
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .filter(|locale| !locale.is_empty() && locale != "*")
+}
+
+fn json_ld(document: Value) -> Response {
+    (
+        StatusCode::OK,
+        AppendHeaders([
+            (header::CONTENT_TYPE, "application/ld+json"),
+            (header::VARY, "Accept-Language"),
+        ]),
+        Json(document),
+    )
+        .into_response()
+}
+
+/// The issuer's Open Badges profile, referenced as `issuer.id` by issued credentials.
+pub async fn get_issuer(Path(slug): Path<String>, headers: HeaderMap) -> Response {
+    if slug != ISSUER_PROFILE_SLUG {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    json_ld(issuer_profile(preferred_locale(&headers).as_deref()))
+}
+
+/// An Open Badges achievement, referenced as `credentialSubject.achievement.id` by issued credentials.
+pub async fn get_achievement(Path(slug): Path<String>, headers: HeaderMap) -> Response {
+    match achievement_definitions()
+        .iter()
+        .find(|achievement| achievement.slug == slug)
+    {
+        Some(achievement) => json_ld(achievement.to_json_ld(preferred_locale(&headers).as_deref())),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// An edition of an achievement in one of the catalog languages, listed as `related` by the achievement.
+pub async fn get_achievement_edition(Path((slug, language)): Path<(String, String)>) -> Response {
+    if !languages().contains(&language.as_str()) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match achievement_definitions()
+        .iter()
+        .find(|achievement| achievement.slug == slug)
+    {
+        Some(achievement) => {
+            let mut document = achievement.to_json_ld(Some(&language));
+            document["id"] = json!(achievement.language_edition_url(&language));
+            json_ld(document)
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /ob3");
+    Router::new()
+        .route("/issuers/:slug", get(get_issuer))
+        .route("/achievements/:slug", get(get_achievement))
+        .route(
+            "/achievements/:slug/:language",
+            get(get_achievement_edition),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_preferred_locale() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(preferred_locale(&headers), None);
+        headers.insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_static("uk-UA,uk;q=0.9,en;q=0.8"),
+        );
+        assert_eq!(preferred_locale(&headers), Some("uk-UA".to_string()));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::access_token::{
+    IntrospectionRequest, RefreshTokenRequest, TokenIntrospection, OFFLINE_ACCESS_SCOPE,
+};
+use crate::authorization_data::CredentialAuthorizationDetails;
+use crate::config::load_credential_response_encryption_required;
+use crate::display::load_display_config;
+use crate::dpop::{validate_dpop_proof, DPOP_HEADER, DPOP_SIGNING_ALG_VALUES_SUPPORTED};
+use crate::encryption::{
+    CredentialResponseEncryption, CREDENTIAL_RESPONSE_ALG_VALUES_SUPPORTED,
+    CREDENTIAL_RESPONSE_ENC_VALUES_SUPPORTED,
+};
+use crate::manager::ManagerType;
+use crate::middleware::admin::AdminAuth;
+use crate::middleware::dpop::AccessToken;
+use crate::signed_metadata::signed_metadata;
+use crate::storage::{get_issuer_did, MemoryStorage};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use axum::routing::post;
+use axum::{Form, Json, Router};
+use oid4vc_core::{Decoder, Subjects};
+use oid4vc_manager::storage::Storage;
+use oid4vci::credential_format_profiles::{CredentialFormats, WithParameters};
+use oid4vci::credential_request::{BatchCredentialRequest, CredentialRequest};
+use oid4vci::credential_response::BatchCredentialResponse;
+use oid4vci::proof::KeyProofType;
+use oid4vci::token_request::TokenRequest;
+use oid4vci::token_response::TokenResponse;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+type CFC = CredentialFormats<WithParameters>;
+
+fn error_response(status: StatusCode, error: &str) -> Response {
+    (status, Json(json!({ "error": error }))).into_response()
+}
+
+pub async fn oauth_authorization_server(State(manager): State<ManagerType>) -> Json<Value> {
+    let mut metadata =
+        serde_json::to_value(&manager.credential_issuer.authorization_server_metadata)
+            .unwrap_or_default();
+    metadata["grant_types_supported"] = json!([
+        "authorization_code",
+        "urn:ietf:params:oauth:grant-type:pre-authorized_code",
+        "refresh_token"
+    ]);
+    metadata["dpop_signing_alg_values_supported"] = json!(DPOP_SIGNING_ALG_VALUES_SUPPORTED);
+    if let Ok(issuer_url) = manager.credential_issuer_url() {
+        metadata["introspection_endpoint"] = json!(issuer_url.join("/introspect").ok());
+    }
+    Json(metadata)
+}
+
+pub async fn openid_credential_issuer(State(manager): State<ManagerType>) -> Json<Value> {
+    let mut metadata =
+        serde_json::to_value(&manager.credential_issuer.metadata).unwrap_or_default();
+    // Display and credential configurations are read again, so changes to their files are served
+    // and signed without a restart
+    match load_display_config() {
+        Ok(display_config) => metadata["display"] = json!(display_config.issuer_display()),
+        Err(e) => log::error!("Failed to load the display configuration: {}", e),
+    }
+    metadata["credential_configurations_supported"] = json!(
+        <MemoryStorage as Storage<CFC>>::get_credential_configurations_supported(&manager.storage)
+    );
+    metadata["credential_response_encryption"] = json!({
+        "alg_values_supported": CREDENTIAL_RESPONSE_ALG_VALUES_SUPPORTED,
+        "enc_values_supported": CREDENTIAL_RESPONSE_ENC_VALUES_SUPPORTED,
+        "encryption_required": load_credential_response_encryption_required(),
+    });
+
+    if let Some(metadata) = metadata.as_object_mut() {
+        metadata.remove("signed_metadata");
+    }
+    match signed_metadata(&metadata) {
+        Ok(jwt) => metadata["signed_metadata"] = json!(jwt),
+        Err(e) => log::error!("Failed to sign the issuer metadata: {}", e),
+    }
+    Json(metadata)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn token(
+    State(manager): State<ManagerType>,
+    headers: HeaderMap,
+    Form(params): Form<HashMap<String, String>>,
+) -> Response {
+    // A DPoP proof binds the access token to the wallet's key
+    let dpop_proof = match headers.get(DPOP_HEADER) {
+        Some(proof) => {
+            let url = match manager.credential_issuer_url() {
+                Ok(issuer_url) => issuer_url.join("/token"),
+                Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            };
+            let proof = match (proof.to_str(), url) {
+                (Ok(proof), Ok(url)) => validate_dpop_proof(proof, "POST", &url, None),
+                _ => return error_response(StatusCode::BAD_REQUEST, "invalid_dpop_proof"),
+            };
+            match proof {
+                Ok(proof) if manager.storage.record_dpop_proof(&proof.jti) => Some(proof),
+                Ok(_) => return error_response(StatusCode::BAD_REQUEST, "invalid_dpop_proof"),
+                Err(e) => {
+                    log::warn!("Invalid DPoP proof: {}", e);
+                    return error_response(StatusCode::BAD_REQUEST, "invalid_dpop_proof");
+                }
+            }
+        }
+        None => None,
+    };
+
+    let dpop_jkt = dpop_proof.map(|proof| proof.jkt);
+
+    let token_response = if params.get("grant_type").map(String::as_str) == Some("refresh_token") {
+        match serde_json::to_value(&params).and_then(serde_json::from_value::<RefreshTokenRequest>)
+        {
+            Ok(request) => manager
+                .storage
+                .refresh_token_response(&request.refresh_token, dpop_jkt),
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid_request"),
+        }
+    } else {
+        let offline_access = params.get("scope").map_or(false, |scope| {
+            scope.split(' ').any(|scope| scope == OFFLINE_ACCESS_SCOPE)
+        });
+        match serde_json::to_value(&params).and_then(serde_json::from_value::<TokenRequest>) {
+            Ok(token_request) => manager.storage.get_bound_token_response(
+                token_request,
+                params.get("client_id").map(String::as_str),
+                dpop_jkt,
+                offline_access,
+            ),
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "unsupported_grant_type"),
+        }
+    };
+
+    match token_response {
+        Some(token_response) => (
+            StatusCode::OK,
+            AppendHeaders([("Cache-Control", "no-store")]),
+            Json(with_authorization_details(&manager, token_response)),
+        )
+            .into_response(),
+        None => error_response(StatusCode::BAD_REQUEST, "invalid_grant"),
+    }
+}
+
+/// Adds one credential identifier per granted certificate to the token response, grouped by
+/// the credential configuration each certificate is issued as.
+fn with_authorization_details(manager: &ManagerType, token_response: TokenResponse) -> Value {
+    let certificate_ids = manager
+        .storage
+        .get_certificate_ids_by_access_token(&token_response.access_token)
+        .unwrap_or_default();
+    let authorization_details =
+        CredentialAuthorizationDetails::for_certificates(certificate_ids, |certificate_id| {
+            manager.storage.credential_configuration_id(certificate_id)
+        });
+    let mut token_response = serde_json::to_value(token_response).unwrap_or_default();
+    if !authorization_details.is_empty() {
+        token_response["authorization_details"] = json!(authorization_details);
+    }
+    token_response
+}
+
+/// Token introspection endpoint (RFC 7662) for debugging, restricted to the admin token.
+#[tracing::instrument(skip_all)]
+pub async fn introspect(
+    State(manager): State<ManagerType>,
+    _admin: AdminAuth,
+    Form(request): Form<IntrospectionRequest>,
+) -> Json<TokenIntrospection> {
+    Json(manager.storage.introspect_access_token(&request.token))
+}
+
+/// Validates the key proof of a credential request and returns the DID of the holder.
+async fn holder_did(manager: &ManagerType, proof: Option<KeyProofType>) -> Option<Url> {
+    let proof = proof?;
+    let subjects = Subjects::try_from([manager.credential_issuer.subject.clone()]).ok()?;
+    let proof = manager
+        .credential_issuer
+        .validate_proof(proof, Decoder::from(&subjects))
+        .await
+        .map_err(|e| log::warn!("Invalid proof: {}", e))
+        .ok()?;
+    proof.rfc7519_claims.iss().as_ref()?.parse().ok()
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn credential(
+    State(manager): State<ManagerType>,
+    AccessToken(access_token): AccessToken,
+    Json(credential_request): Json<Value>,
+) -> Response {
+    let encryption = match response_encryption(&credential_request) {
+        Ok(encryption) => encryption,
+        Err(response) => return response,
+    };
+
+    // Wallets that received credential identifiers in the token response pick the certificate by its identifier
+    if let Some(credential_identifier) = credential_request["credential_identifier"].as_str() {
+        let granted = manager
+            .storage
+            .get_certificate_ids_by_access_token(&access_token)
+            .unwrap_or_default();
+        if !granted.iter().any(|id| id == credential_identifier) {
+            return error_response(StatusCode::BAD_REQUEST, "unknown_credential_identifier");
+        }
+        let proof = serde_json::from_value(credential_request["proof"].clone()).ok();
+        let subject_did = match holder_did(&manager, proof).await {
+            Some(subject_did) => subject_did,
+            None => return error_response(StatusCode::BAD_REQUEST, "invalid_proof"),
+        };
+        // Each identifier is issued once per grant, like certificates claimed in order
+        if !manager
+            .storage
+            .claim_certificate_identifier(&access_token, credential_identifier)
+        {
+            return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request");
+        }
+        return credential_response(
+            manager.storage.issue_certificate_credential(
+                &access_token,
+                Some(credential_identifier.to_string()),
+                subject_did,
+                get_issuer_did(),
+                manager.credential_issuer.subject.clone(),
+            ),
+            encryption.as_ref(),
+        );
+    }
+
+    let credential_request: CredentialRequest<CFC> =
+        match serde_json::from_value(credential_request) {
+            Ok(credential_request) => credential_request,
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request"),
+        };
+    let subject_did = match holder_did(&manager, credential_request.proof.clone()).await {
+        Some(subject_did) => subject_did,
+        None => return error_response(StatusCode::BAD_REQUEST, "invalid_proof"),
+    };
+    let issuer_url = match manager.credential_issuer_url() {
+        Ok(issuer_url) => issuer_url,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    credential_response(
+        manager.storage.get_credential_response(
+            access_token,
+            subject_did,
+            issuer_url,
+            credential_request.credential_format,
+            manager.credential_issuer.subject.clone(),
+        ),
+        encryption.as_ref(),
+    )
+}
+
+/// Reads and checks the wallet's `credential_response_encryption` parameters before anything is
+/// issued, they are mandatory if `CREDENTIAL_RESPONSE_ENCRYPTION=required`.
+pub fn response_encryption(
+    request: &Value,
+) -> Result<Option<CredentialResponseEncryption>, Response> {
+    CredentialResponseEncryption::from_request(
+        request,
+        load_credential_response_encryption_required(),
+    )
+    .map_err(|e| {
+        log::warn!("Invalid credential response encryption: {}", e);
+        error_response(StatusCode::BAD_REQUEST, "invalid_encryption_parameters")
+    })
+}
+
+/// Responds with the credential response, as JWE if the wallet asked for encryption.
+pub fn credential_response<T: Serialize>(
+    credential_response: Option<T>,
+    encryption: Option<&CredentialResponseEncryption>,
+) -> Response {
+    let credential_response = match credential_response {
+        Some(credential_response) => credential_response,
+        None => return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request"),
+    };
+    let encryption = match encryption {
+        Some(encryption) => encryption,
+        None => {
+            return (
+                StatusCode::OK,
+                AppendHeaders([("Cache-Control", "no-store")]),
+                Json(credential_response),
+            )
+                .into_response()
+        }
+    };
+
+    match serde_json::to_vec(&credential_response)
+        .map_err(anyhow::Error::from)
+        .and_then(|payload| encryption.encrypt(&payload))
+    {
+        Ok(jwe) => (
+            StatusCode::OK,
+            AppendHeaders([
+                ("Cache-Control", "no-store"),
+                ("Content-Type", "application/jwt"),
+            ]),
+            jwe,
+        )
+            .into_response(),
+        Err(e) => {
+            log::warn!("Failed to encrypt the credential response: {}", e);
+            error_response(StatusCode::BAD_REQUEST, "invalid_encryption_parameters")
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn batch_credential(
+    State(manager): State<ManagerType>,
+    AccessToken(access_token): AccessToken,
+    Json(batch_credential_request): Json<Value>,
+) -> Response {
+    let encryption = match response_encryption(&batch_credential_request) {
+        Ok(encryption) => encryption,
+        Err(response) => return response,
+    };
+    let batch_credential_request: BatchCredentialRequest<CFC> =
+        match serde_json::from_value(batch_credential_request) {
+            Ok(batch_credential_request) => batch_credential_request,
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request"),
+        };
+
+    // Validate all requests before anything is issued, a batch is granted as a whole or not at all
+    let mut subject_dids = vec![];
+    for credential_request in &batch_credential_request.credential_requests {
+        match holder_did(&manager, credential_request.proof.clone()).await {
+            Some(subject_did) => subject_dids.push(subject_did),
+            None => return error_response(StatusCode::BAD_REQUEST, "invalid_proof"),
+        }
+    }
+    let certificate_ids = match manager
+        .storage
+        .claim_certificate_ids(&access_token, subject_dids.len())
+    {
+        Some(certificate_ids) => certificate_ids,
+        None => return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request"),
+    };
+    if certificate_ids
+        .iter()
+        .any(|certificate_id| manager.storage.is_revoked(certificate_id))
+    {
+        return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request");
+    }
+
+    let mut credential_responses = vec![];
+    let mut c_nonce = None;
+    let mut c_nonce_expires_in = None;
+    for (certificate_id, subject_did) in certificate_ids.into_iter().zip(subject_dids) {
+        match manager.storage.issue_certificate_credential(
+            &access_token,
+            Some(certificate_id),
+            subject_did,
+            get_issuer_did(),
+            manager.credential_issuer.subject.clone(),
+        ) {
+            Some(credential_response) => {
+                c_nonce = credential_response.c_nonce;
+                c_nonce_expires_in = credential_response.c_nonce_expires_in;
+                credential_responses.push(credential_response.credential);
+            }
+            None => return error_response(StatusCode::BAD_REQUEST, "invalid_credential_request"),
+        }
+    }
+
+    credential_response(
+        Some(BatchCredentialResponse {
+            credential_responses,
+            c_nonce,
+            c_nonce_expires_in,
+        }),
+        encryption.as_ref(),
+    )
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for the OID4VCI endpoints");
+    Router::new()
+        .route("/token", post(token))
+        .route("/introspect", post(introspect))
+        .route("/credential", post(credential))
+        .route("/batch_credential", post(batch_credential))
+}
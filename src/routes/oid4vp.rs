@@ -0,0 +1,61 @@
+use crate::manager::ManagerType;
+use crate::presentation_data::PresentationResponse;
+use crate::services::{PresentationService, REQUEST_OBJECT_TYPE};
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use serde_json::json;
+
+/// Signed request object of a presentation request, fetched by the wallet via `request_uri`.
+#[tracing::instrument(skip_all)]
+pub async fn get_request_object(
+    State(manager): State<ManagerType>,
+    Path(session_id): Path<String>,
+) -> Response {
+    match PresentationService::new(&manager).request_object(&session_id) {
+        Ok(request_object) => (
+            StatusCode::OK,
+            AppendHeaders([(
+                header::CONTENT_TYPE,
+                format!("application/{}", REQUEST_OBJECT_TYPE),
+            )]),
+            request_object,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
+/// Authorization response of the wallet, posted with `response_mode=direct_post`.
+#[tracing::instrument(skip_all)]
+pub async fn post_response(
+    State(manager): State<ManagerType>,
+    Form(response): Form<PresentationResponse>,
+) -> Response {
+    match PresentationService::new(&manager)
+        .handle_response(response)
+        .await
+    {
+        Ok(_) => (StatusCode::OK, Json(json!({}))).into_response(),
+        Err(e) => {
+            log::warn!("Invalid presentation response: {}", e);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "invalid_request",
+                    "error_description": e.to_string(),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /oid4vp");
+    Router::new()
+        .route("/oid4vp/requests/:session_id", get(get_request_object))
+        .route("/oid4vp/response", post(post_response))
+}
@@ -1,10 +1,14 @@
+use crate::admin;
 use crate::certificate_data::CertificateData;
 use crate::manager::ManagerType;
+use crate::middleware::learner::LearnerAuth;
+use crate::public_verification::VisibilityRequest;
 use crate::services::CertificateService;
+use crate::verification::{CredentialVerifier, VerificationReport, VerificationRequest};
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::routing::post;
+use axum::routing::{post, put};
 use axum::{Json, Router};
 
 pub async fn send_certificate_and_get_qr(
@@ -30,9 +34,50 @@ pub async fn post_certificate_and_get_offer(
     Ok(Json(offer_url))
 }
 
+pub async fn post_certificates_and_get_batch_offer(
+    State(manager): State<ManagerType>,
+    Json(certificates): Json<Vec<CertificateData>>,
+) -> Result<Json<String>, (StatusCode, String)> {
+    let service = CertificateService::new(&manager);
+    service
+        .generate_batch_offer_url(&certificates)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Verifies a credential a learner sent, e.g. with a support ticket or job application.
+pub async fn post_verify(
+    State(manager): State<ManagerType>,
+    Json(request): Json<VerificationRequest>,
+) -> Json<VerificationReport> {
+    let verifier = CredentialVerifier::new().with_storage(manager.storage.clone());
+    Json(verifier.verify(&request).await)
+}
+
+/// Lets a learner opt in or out of the public verification page of their credentials.
+pub async fn put_learner_visibility(
+    State(manager): State<ManagerType>,
+    LearnerAuth(learner): LearnerAuth,
+    Json(request): Json<VisibilityRequest>,
+) -> Json<VisibilityRequest> {
+    manager
+        .storage
+        .set_public_visibility(&learner.profile_id, request.public);
+    Json(VisibilityRequest {
+        public: manager.storage.is_publicly_visible(&learner.profile_id),
+    })
+}
+
 pub fn create_router() -> Router<ManagerType> {
     log::info!("Creating router for /api/v1");
     Router::new()
         .route("/certificates/qr", post(send_certificate_and_get_qr))
         .route("/certificates/offer", post(post_certificate_and_get_offer))
+        .route(
+            "/certificates/offer/batch",
+            post(post_certificates_and_get_batch_offer),
+        )
+        .route("/verify", post(post_verify))
+        .route("/learner/visibility", put(put_learner_visibility))
+        .nest("/admin", admin::create_router())
 }
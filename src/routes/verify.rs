@@ -0,0 +1,69 @@
+use crate::manager::ManagerType;
+use crate::public_verification::verification_url;
+use crate::services::qr_to_png;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{AppendHeaders, Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use qrcodegen::{QrCode, QrCodeEcc};
+
+/// Whether the client asked for JSON rather than the HTML page.
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |accept| {
+            accept.contains("application/json") && !accept.contains("text/html")
+        })
+}
+
+/// Public verification page of an issued credential, as HTML or as JSON.
+#[tracing::instrument(skip_all)]
+pub async fn get_verification(
+    State(manager): State<ManagerType>,
+    Path(credential_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    // Unknown credentials and those of learners who opted out look the same
+    let Some(verification) = manager.storage.get_public_verification(&credential_id) else {
+        return (StatusCode::NOT_FOUND, "Credential not found").into_response();
+    };
+    let vary = AppendHeaders([(header::VARY, "Accept")]);
+    match wants_json(&headers) {
+        true => (vary, Json(verification)).into_response(),
+        false => (vary, Html(verification.to_html())).into_response(),
+    }
+}
+
+/// QR code of the verification page, e.g. to print on a certificate.
+pub async fn get_verification_qr(
+    State(manager): State<ManagerType>,
+    Path(credential_id): Path<String>,
+) -> Response {
+    if manager
+        .storage
+        .get_public_verification(&credential_id)
+        .is_none()
+    {
+        return (StatusCode::NOT_FOUND, "Credential not found").into_response();
+    }
+    let png_data = QrCode::encode_text(&verification_url(&credential_id), QrCodeEcc::Medium)
+        .map_err(anyhow::Error::from)
+        .and_then(|qr| qr_to_png(&qr));
+    match png_data {
+        Ok(png_data) => (
+            AppendHeaders([(header::CONTENT_TYPE, "image/png")]),
+            png_data,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+pub fn create_router() -> Router<ManagerType> {
+    log::info!("Creating router for /verify");
+    Router::new()
+        .route("/:credential_id", get(get_verification))
+        .route("/:credential_id/qr.png", get(get_verification_qr))
+}
@@ -1,12 +1,13 @@
-use crate::config::load_config;
+use crate::config::load_private_key;
 use crate::manager::ManagerType;
+use crate::oid4vci;
 use axum::http::StatusCode;
 use axum::routing::get;
 use axum::{Json, Router};
 use did_key::{generate, DIDCore, Document, Ed25519KeyPair, PatchedKeyPair};
 
 pub async fn get_did_document() -> Result<Json<Document>, (StatusCode, String)> {
-    let (priv_key, _) = load_config();
+    let priv_key = load_private_key();
 
     let issuer_key: PatchedKeyPair = generate::<Ed25519KeyPair>(Some(priv_key.as_bytes()));
 
@@ -16,6 +17,15 @@ pub async fn get_did_document() -> Result<Json<Document>, (StatusCode, String)>
 }
 
 pub fn create_router() -> Router<ManagerType> {
-    log::info!("Creating router for /.well-known/did.json");
-    Router::new().route("/did.json", get(get_did_document))
+    log::info!("Creating router for /.well-known");
+    Router::new()
+        .route("/did.json", get(get_did_document))
+        .route(
+            "/oauth-authorization-server",
+            get(oid4vci::oauth_authorization_server),
+        )
+        .route(
+            "/openid-credential-issuer",
+            get(oid4vci::openid_credential_issuer),
+        )
 }
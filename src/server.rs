@@ -1,14 +1,20 @@
-use crate::config::{load_config, Config};
+use crate::config::{load_private_key, load_storage_file, Config};
 use crate::manager::ConfigurableManager;
 #[cfg(feature = "metrics")]
 use crate::middleware;
+use crate::oidc::init_oidc_client;
 use crate::storage::MemoryStorage;
+use crate::webhooks::{load_webhook_endpoints, WebhookDispatcher};
 use crate::{assets, create_example_router, manager::ManagerType};
-use crate::{health, v1, well_known};
+use crate::{
+    authorize, deferred_credential, health, notification, ob3, oid4vci, oid4vp, v1, verify,
+    well_known,
+};
 use anyhow::Result;
 use axum::{routing::get, Router};
 use did_key::{generate, DIDCore, Ed25519KeyPair, PatchedKeyPair};
-use oid4vc_manager::{methods::key_method::KeySubject, servers::credential_issuer::Server};
+use oid4vc_manager::methods::key_method::KeySubject;
+use std::net::TcpListener;
 use std::sync::Arc;
 use tower_http::trace::{self, TraceLayer};
 use tracing::Level;
@@ -18,16 +24,17 @@ use std::sync::OnceLock;
 #[cfg(feature = "metrics")]
 pub static GLOBAL_METRICS: OnceLock<crate::metrics::Metrics> = OnceLock::new();
 
-pub async fn start_server() -> Result<()> {
-    #[cfg(feature = "metrics")]
-    {
-        let metrics = crate::metrics::Metrics::new().expect("Failed to initialize metrics");
-        if let Err(_) = GLOBAL_METRICS.set(metrics) {
-            log::warn!("Global metrics already initialized");
-        }
+/// Storage of the issuer, kept in the `STORAGE_FILE` if one is configured.
+pub fn create_storage() -> Result<MemoryStorage> {
+    match load_storage_file() {
+        Some(storage_file) => MemoryStorage::new().with_storage_file(storage_file),
+        None => Ok(MemoryStorage::new()),
     }
+}
 
-    let (priv_key, _) = load_config();
+/// Credential issuer manager signing with the configured issuer key.
+pub fn create_manager(listener: TcpListener, storage: MemoryStorage) -> Result<ManagerType> {
+    let priv_key = load_private_key();
 
     let issuer_key: PatchedKeyPair = generate::<Ed25519KeyPair>(Some(priv_key.as_bytes()));
 
@@ -37,20 +44,45 @@ pub async fn start_server() -> Result<()> {
 
     log::debug!("Issuer Subject: {:?}", document);
 
+    let config = Config::default();
+
+    ManagerType::with_config(Some(listener), storage, Arc::new(issuer_subject), config)
+}
+
+pub async fn start_server() -> Result<()> {
+    #[cfg(feature = "metrics")]
+    {
+        let metrics = crate::metrics::Metrics::new().expect("Failed to initialize metrics");
+        if let Err(_) = GLOBAL_METRICS.set(metrics) {
+            log::warn!("Global metrics already initialized");
+        }
+    }
+
+    init_oidc_client()?;
+
     let listener = std::net::TcpListener::bind(std::net::SocketAddr::new(
         std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
         3000,
     ))?;
 
-    let config = Config::default();
+    let storage =
+        create_storage()?.with_webhooks(WebhookDispatcher::new(load_webhook_endpoints()?));
+
+    // Periodically expire offers that were never redeemed and pick up changes to the storage file
+    let expiring_storage = storage.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = expiring_storage.reload() {
+                log::error!("Failed to reload the storage file: {}", e);
+            }
+            expiring_storage.expire_offers();
+        }
+    });
 
     // Create a CredentialIssuerManager
-    let credential_issuer_manager = ManagerType::with_config(
-        Some(listener),
-        MemoryStorage::new(),
-        Arc::new(issuer_subject),
-        config,
-    )?;
+    let credential_issuer_manager = create_manager(listener, storage)?;
 
     let trace_layer = TraceLayer::new_for_http()
         .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
@@ -62,7 +94,14 @@ pub async fn start_server() -> Result<()> {
     let mut app = Router::new()
         .route("/health", axum::routing::get(health::health_check))
         .route("/ready", axum::routing::get(health::readiness_check))
+        .merge(oid4vci::create_router())
+        .merge(oid4vp::create_router())
+        .merge(authorize::create_router())
+        .merge(notification::create_router())
+        .merge(deferred_credential::create_router())
+        .nest("/ob3", ob3::create_router())
         .nest("/api/v1", v1::create_router())
+        .nest("/verify", verify::create_router())
         .nest("/example", create_example_router())
         .nest("/.well-known", well_known::create_router())
         .nest("/", assets::create_router());
@@ -79,16 +118,18 @@ pub async fn start_server() -> Result<()> {
 
     let app = app.layer(trace_layer);
 
-    // Initialize the server with the app as the extension router
-    let mut server = Server::setup(credential_issuer_manager, Some(app))?;
-
     // Get the credential issuer URL
-    let credential_issuer_url = server.credential_issuer_manager.credential_issuer_url()?;
+    let credential_issuer_url = credential_issuer_manager.credential_issuer_url()?;
     println!("Credential Issuer URL: {}", credential_issuer_url);
 
+    let listener = credential_issuer_manager.listener.try_clone()?;
+    let app = app.with_state(credential_issuer_manager);
+
     // Start the server
     println!("Starting the Credential Issuer server...");
-    server.start_server().await?;
+    axum::Server::from_tcp(listener)?
+        .serve(app.into_make_service())
+        .await?;
 
     Ok(())
 }
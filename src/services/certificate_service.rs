@@ -1,10 +1,65 @@
-use crate::certificate_data::CertificateData;
+use crate::certificate_data::{CertificateData, CERTIFICATE_CREDENTIAL_CONFIGURATION_ID};
+use crate::clr_data::CLR_CREDENTIAL_CONFIGURATION_ID;
 use crate::manager::ManagerType;
+use crate::webhooks::WebhookEventType;
 use anyhow::Result;
+use image::{ImageBuffer, Rgb};
 use qrcodegen::{QrCode, QrCodeEcc};
+use serde_json::json;
+use std::io::Cursor;
 use url::Url;
 use uuid::Uuid;
 
+/// Renders a QR code as text, one block character per dark module.
+pub fn qr_to_string(qr: &QrCode) -> String {
+    let size = qr.size();
+    let mut result = String::new();
+    for y in 0..size {
+        for x in 0..size {
+            result.push(if qr.get_module(x, y) { '█' } else { ' ' });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Renders a QR code as PNG, with white padding around it.
+pub fn qr_to_png(qr: &QrCode) -> Result<Vec<u8>> {
+    // Set the scale factor to make each QR module larger
+    let scale = 10;
+    let padding = 40; // Add padding around the QR code
+    let size = qr.size() as u32;
+    let img_size = size * scale + 2 * padding;
+
+    // Fill the entire image with white (including padding)
+    let mut img: ImageBuffer<Rgb<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(img_size, img_size, Rgb([255u8, 255u8, 255u8]));
+
+    // Draw the QR code on the image (with padding offset)
+    for y in 0..size {
+        for x in 0..size {
+            if qr.get_module(x as i32, y as i32) {
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        img.put_pixel(
+                            x * scale + dx + padding,
+                            y * scale + dy + padding,
+                            Rgb([0u8, 0u8, 0u8]),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let mut png_data = Vec::new();
+    img.write_to(
+        &mut Cursor::new(&mut png_data),
+        image::ImageOutputFormat::Png,
+    )?;
+    Ok(png_data)
+}
+
 pub struct CertificateService<'a> {
     manager: &'a ManagerType,
 }
@@ -19,22 +74,75 @@ impl<'a> CertificateService<'a> {
 
         let qr = QrCode::encode_text(&offer_url, QrCodeEcc::Medium)?;
 
-        Ok(self.qr_to_string(&qr))
+        Ok(qr_to_string(&qr))
     }
 
-    fn qr_to_string(&self, qr: &QrCode) -> String {
-        let size = qr.size();
-        let mut result = String::new();
-        for y in 0..size {
-            for x in 0..size {
-                result.push(if qr.get_module(x, y) { '█' } else { ' ' });
-            }
-            result.push('\n');
+    pub fn generate_offer_url(&self, certificate_data: &CertificateData) -> Result<String> {
+        self.generate_batch_offer_url(std::slice::from_ref(certificate_data))
+    }
+
+    /// Creates a single pre-authorized offer that bundles all given certificates.
+    pub fn generate_batch_offer_url(&self, certificates: &[CertificateData]) -> Result<String> {
+        if certificates.is_empty() {
+            return Err(anyhow::anyhow!("An offer needs at least one certificate"));
         }
-        result
+
+        // Store the certificates under unique ids
+        let certificate_ids: Vec<String> = certificates
+            .iter()
+            .map(|certificate_data| {
+                let certificate_id = Uuid::new_v4().to_string();
+                self.manager
+                    .storage
+                    .store_certificate(certificate_id.clone(), certificate_data.clone());
+                certificate_id
+            })
+            .collect();
+        let offer_url =
+            self.create_offer(&certificate_ids, CERTIFICATE_CREDENTIAL_CONFIGURATION_ID)?;
+
+        self.manager.storage.notify(
+            WebhookEventType::OfferCreated,
+            json!({
+                "certificate_ids": certificate_ids,
+                "game_path_names": certificates
+                    .iter()
+                    .map(|certificate| certificate.game_path_name.clone())
+                    .collect::<Vec<_>>(),
+                "profile_name": certificates[0].profile_name,
+            }),
+        );
+        Ok(offer_url)
     }
 
-    pub fn generate_offer_url(&self, certificate_data: &CertificateData) -> Result<String> {
+    /// Creates an offer of a Comprehensive Learner Record with all certificates of a learner.
+    pub fn generate_clr_offer_url(&self, profile_id: &str) -> Result<String> {
+        let clr_id = self
+            .manager
+            .storage
+            .create_clr_bundle(profile_id)
+            .ok_or_else(|| anyhow::anyhow!("No certificates found for profile {}", profile_id))?;
+        let offer_url = self.create_offer(
+            std::slice::from_ref(&clr_id),
+            CLR_CREDENTIAL_CONFIGURATION_ID,
+        )?;
+
+        self.manager.storage.notify(
+            WebhookEventType::OfferCreated,
+            json!({
+                "certificate_ids": [clr_id],
+                "profile_id": profile_id,
+            }),
+        );
+        Ok(offer_url)
+    }
+
+    /// Creates a pre-authorized offer of the given credential configuration for the certificate ids.
+    fn create_offer(
+        &self,
+        certificate_ids: &[String],
+        credential_configuration_id: &str,
+    ) -> Result<String> {
         let offer_url = self.manager.credential_offer_query(false)?;
 
         // Parse the URL and extract the credential_offer parameter
@@ -47,7 +155,7 @@ impl<'a> CertificateService<'a> {
 
         // Decode and parse the credential offer JSON
         let decoded_offer = urlencoding::decode(&credential_offer)?;
-        let offer_json: serde_json::Value = serde_json::from_str(&decoded_offer)?;
+        let mut offer_json: serde_json::Value = serde_json::from_str(&decoded_offer)?;
 
         // Extract the pre-authorized code
         let pre_authorized_code = offer_json["grants"]
@@ -56,17 +164,28 @@ impl<'a> CertificateService<'a> {
             .ok_or_else(|| anyhow::anyhow!("No pre-authorized code found"))?
             .to_string();
 
-        // Generate a unique certificate ID
-        let certificate_id = Uuid::new_v4().to_string();
-
-        // Store the certificate data and associate it with the pre-authorized code
-        self.manager
-            .storage
-            .store_certificate(certificate_id.clone(), certificate_data.clone());
+        // Associate the certificates with the pre-authorized code
         self.manager
             .storage
-            .associate_pre_authorized_code(pre_authorized_code.clone(), certificate_id);
-        Ok(offer_url)
+            .associate_pre_authorized_code(pre_authorized_code.clone(), certificate_ids.to_vec());
+
+        // Bind the issuer state as well, so the offer can be redeemed with the authorization code flow
+        if let Some(issuer_state) =
+            offer_json["grants"]["authorization_code"]["issuer_state"].as_str()
+        {
+            self.manager
+                .storage
+                .associate_issuer_state(issuer_state.to_string(), certificate_ids.to_vec());
+        }
+
+        // The issuer offers all of its credential configurations, narrow the offer down to one
+        offer_json["credential_configuration_ids"] = json!([credential_configuration_id]);
+        let mut offer_url = parsed_url;
+        offer_url.set_query(Some(&format!(
+            "credential_offer={}",
+            urlencoding::encode(&offer_json.to_string())
+        )));
+        Ok(offer_url.to_string())
     }
 
     pub fn get_certificate(&self, offer_id: &str) -> Option<CertificateData> {
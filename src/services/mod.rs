@@ -1,3 +1,5 @@
 mod certificate_service;
+mod presentation_service;
 
-pub use certificate_service::CertificateService;
+pub use certificate_service::{qr_to_png, qr_to_string, CertificateService};
+pub use presentation_service::{PresentationService, REQUEST_OBJECT_TYPE};
@@ -0,0 +1,406 @@
+use crate::config::load_issuer_url;
+use crate::issuer_key::sign_with_issuer_key;
+use crate::manager::ManagerType;
+use crate::presentation_data::{
+    authorization_request, performance_of, PresentationRequestCreate, PresentationRequestCreated,
+    PresentationResponse, PresentationResult, PresentationSession, PresentationStatus,
+    PRESENTATION_REQUEST_TTL_MINUTES,
+};
+use crate::services::qr_to_string;
+use crate::storage::get_issuer_did;
+use crate::verification::{
+    CheckStatus, CredentialVerifier, VerificationReport, VerificationRequest,
+};
+use crate::webhooks::WebhookEventType;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use oid4vc_core::generate_authorization_code;
+use qrcodegen::{QrCode, QrCodeEcc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Media type of signed OpenID4VP request objects.
+pub const REQUEST_OBJECT_TYPE: &str = "oauth-authz-req+jwt";
+
+/// Relying party that asks wallets to present Konnektoren certificates with OpenID4VP.
+pub struct PresentationService<'a> {
+    manager: &'a ManagerType,
+}
+
+impl<'a> PresentationService<'a> {
+    pub fn new(manager: &'a ManagerType) -> Self {
+        Self { manager }
+    }
+
+    fn request_uri(session_id: &str) -> String {
+        format!(
+            "{}/oid4vp/requests/{}",
+            load_issuer_url().trim_end_matches('/'),
+            session_id
+        )
+    }
+
+    fn response_uri() -> String {
+        format!(
+            "{}/oid4vp/response",
+            load_issuer_url().trim_end_matches('/')
+        )
+    }
+
+    /// Starts a presentation session and returns the `openid4vp://` URL for the learner's wallet.
+    pub fn create_request(
+        &self,
+        request: PresentationRequestCreate,
+    ) -> Result<PresentationRequestCreated> {
+        if request.min_performance > 100 {
+            return Err(anyhow!("min_performance must be between 0 and 100"));
+        }
+        let created_at = Utc::now();
+        let session = PresentationSession {
+            id: Uuid::new_v4().to_string(),
+            nonce: generate_authorization_code(16),
+            min_performance: request.min_performance,
+            query_language: request.query_language,
+            partner_id: request.partner_id,
+            status: PresentationStatus::Pending,
+            created_at,
+            expires_at: created_at + Duration::minutes(PRESENTATION_REQUEST_TTL_MINUTES),
+            result: None,
+        };
+        self.manager
+            .storage
+            .store_presentation_session(session.clone());
+
+        let request_url = format!(
+            "openid4vp://?client_id={}&request_uri={}",
+            urlencoding::encode(&get_issuer_did()),
+            urlencoding::encode(&Self::request_uri(&session.id))
+        );
+        let qr = QrCode::encode_text(&request_url, QrCodeEcc::Medium)?;
+        Ok(PresentationRequestCreated {
+            id: session.id,
+            qr_code: qr_to_string(&qr),
+            request_url,
+            expires_at: session.expires_at,
+        })
+    }
+
+    /// The signed request object of a pending session, as fetched by the wallet.
+    pub fn request_object(&self, session_id: &str) -> Result<String> {
+        let session = self
+            .manager
+            .storage
+            .get_presentation_session(session_id)
+            .filter(|session| {
+                session.status == PresentationStatus::Pending && session.expires_at > Utc::now()
+            })
+            .ok_or_else(|| anyhow!("Unknown or expired presentation request {}", session_id))?;
+        let claims = authorization_request(&session, &get_issuer_did(), &Self::response_uri());
+        sign_with_issuer_key(REQUEST_OBJECT_TYPE, &claims)
+    }
+
+    pub fn get_session(&self, session_id: &str) -> Option<PresentationSession> {
+        self.manager.storage.get_presentation_session(session_id)
+    }
+
+    /// Verifies the authorization response of a wallet and records the outcome in its session.
+    pub async fn handle_response(
+        &self,
+        response: PresentationResponse,
+    ) -> Result<PresentationSession> {
+        let session_id = response
+            .state
+            .clone()
+            .ok_or_else(|| anyhow!("The response has no state"))?;
+        let session = self
+            .manager
+            .storage
+            .get_presentation_session(&session_id)
+            .filter(|session| session.status == PresentationStatus::Pending)
+            .ok_or_else(|| anyhow!("Unknown or completed presentation request {}", session_id))?;
+
+        let result = match (&response.error, session.expires_at > Utc::now()) {
+            (Some(error), _) => PresentationResult {
+                errors: vec![format!(
+                    "The wallet declined the request: {} {}",
+                    error,
+                    response.error_description.as_deref().unwrap_or_default()
+                )
+                .trim_end()
+                .to_string()],
+                ..Default::default()
+            },
+            (None, false) => PresentationResult {
+                errors: vec!["The presentation request expired".to_string()],
+                ..Default::default()
+            },
+            (None, true) => self.verify_response(&session, &response).await,
+        };
+        let status = match result.errors.is_empty() && result.performance_percentage.is_some() {
+            true => PresentationStatus::Verified,
+            false => PresentationStatus::Rejected,
+        };
+        log::info!(
+            "Presentation {} {:?}: {:?}",
+            session_id,
+            status,
+            result.errors
+        );
+        let session = self
+            .manager
+            .storage
+            .complete_presentation_session(&session_id, status, result)
+            .ok_or_else(|| {
+                anyhow!(
+                    "The presentation request {} was already answered",
+                    session_id
+                )
+            })?;
+        self.manager.storage.notify(
+            WebhookEventType::PresentationCompleted,
+            json!({
+                "presentation_id": session.id,
+                "partner_id": session.partner_id,
+                "status": session.status,
+            }),
+        );
+        Ok(session)
+    }
+
+    async fn verify_response(
+        &self,
+        session: &PresentationSession,
+        response: &PresentationResponse,
+    ) -> PresentationResult {
+        let mut result = PresentationResult::default();
+        let vp_tokens = match response.vp_token.as_deref().map(vp_tokens) {
+            Some(vp_tokens) if !vp_tokens.is_empty() => vp_tokens,
+            _ => {
+                result
+                    .errors
+                    .push("The response has no vp_token".to_string());
+                return result;
+            }
+        };
+
+        let verifier = CredentialVerifier::new().with_storage(self.manager.storage.clone());
+        let issuer_did = get_issuer_did();
+        for vp_token in vp_tokens {
+            let presentation = match verifier.verify_jwt(&vp_token).await {
+                Ok(presentation) => presentation,
+                Err(e) => {
+                    result.errors.push(format!("Invalid presentation: {}", e));
+                    continue;
+                }
+            };
+            if presentation["nonce"].as_str() != Some(session.nonce.as_str()) {
+                result
+                    .errors
+                    .push("The presentation does not answer this request".to_string());
+                continue;
+            }
+            let audience_matches = match &presentation["aud"] {
+                Value::Array(audiences) => audiences.iter().any(|aud| aud == &json!(issuer_did)),
+                aud => aud == &json!(issuer_did),
+            };
+            if !audience_matches {
+                result
+                    .errors
+                    .push("The presentation is meant for another verifier".to_string());
+                continue;
+            }
+            let holder = presentation["iss"].as_str().map(str::to_string);
+            result.holder = holder.clone();
+
+            let credentials = match &presentation["vp"]["verifiableCredential"] {
+                Value::Array(credentials) => credentials.clone(),
+                credential => vec![credential.clone()],
+            };
+            for credential in credentials.iter().filter_map(Value::as_str) {
+                let report = verifier
+                    .verify(&VerificationRequest::jwt_vc(credential))
+                    .await;
+                let errors = self.check_credential(session, holder.as_deref(), &report);
+                if errors.is_empty() && result.performance_percentage.is_none() {
+                    let credential = report.credential.as_ref().unwrap_or(&Value::Null);
+                    result.performance_percentage = performance_of(credential);
+                    result.game_path_name = credential["credentialSubject"]["achievement"]["name"]
+                        .as_str()
+                        .map(str::to_string);
+                }
+                result.errors.extend(errors);
+                result.credentials.push(report);
+            }
+        }
+        if result.performance_percentage.is_some() {
+            // One certificate that meets the requirements is enough
+            result.errors.clear();
+        } else if result.errors.is_empty() {
+            result
+                .errors
+                .push("No Konnektoren certificate was presented".to_string());
+        }
+        result
+    }
+
+    fn check_credential(
+        &self,
+        session: &PresentationSession,
+        holder: Option<&str>,
+        report: &VerificationReport,
+    ) -> Vec<String> {
+        let mut errors: Vec<String> = report
+            .checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .map(|check| {
+                format!(
+                    "Check {} failed: {}",
+                    check.check,
+                    check.message.as_deref().unwrap_or_default()
+                )
+            })
+            .collect();
+        if report.issuer.as_deref() != Some(get_issuer_did().as_str()) {
+            errors.push(format!(
+                "The credential was not issued by Konnektoren but by {:?}",
+                report.issuer
+            ));
+        }
+        // Konnektoren credentials carry no status list, the issuer's storage must have vouched for them
+        let revocation_checked = report
+            .checks
+            .iter()
+            .any(|check| check.check == "revocation" && check.status == CheckStatus::Passed);
+        if !revocation_checked {
+            errors.push("The revocation of the credential could not be checked".to_string());
+        }
+        if holder.is_none() || report.subject.as_deref() != holder {
+            errors
+                .push("The credential was presented by someone else than its subject".to_string());
+        }
+        if !report
+            .credential_types
+            .iter()
+            .any(|t| t == "OpenBadgeCredential")
+        {
+            errors.push("The credential is not a Konnektoren certificate".to_string());
+        }
+        match report.credential.as_ref().and_then(performance_of) {
+            Some(performance) if performance >= session.min_performance => {}
+            Some(performance) => errors.push(format!(
+                "The performance of {}% is below the required {}%",
+                performance, session.min_performance
+            )),
+            None => errors.push("The credential states no performance".to_string()),
+        }
+        errors
+    }
+}
+
+/// VP tokens of a response, either a single JWT, a JSON array of them or a DCQL object of query id to JWT(s).
+fn vp_tokens(vp_token: &str) -> Vec<String> {
+    fn collect(value: &Value, tokens: &mut Vec<String>) {
+        match value {
+            Value::String(token) => tokens.push(token.clone()),
+            Value::Array(values) => values.iter().for_each(|value| collect(value, tokens)),
+            Value::Object(values) => values.values().for_each(|value| collect(value, tokens)),
+            _ => {}
+        }
+    }
+    let mut tokens = vec![];
+    match serde_json::from_str::<Value>(vp_token.trim()) {
+        Ok(value) => collect(&value, &mut tokens),
+        Err(_) => tokens.push(vp_token.trim().to_string()),
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate_data::CertificateData;
+    use crate::config::Config;
+    use crate::issuer_key::issuer_key;
+    use crate::manager::ConfigurableManager;
+    use crate::presentation_data::QueryLanguage;
+    use crate::storage::MemoryStorage;
+    use oid4vc_manager::methods::key_method::KeySubject;
+    use oid4vci::credential_response::CredentialResponseType;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_vp_tokens() {
+        assert_eq!(vp_tokens("a.b.c"), vec!["a.b.c"]);
+        assert_eq!(vp_tokens(r#"["a.b.c", "d.e.f"]"#), vec!["a.b.c", "d.e.f"]);
+        assert_eq!(
+            vp_tokens(r#"{"konnektoren_certificate": ["a.b.c"]}"#),
+            vec!["a.b.c"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoked_credential_is_rejected() {
+        crate::config::set_test_env();
+        let manager = ManagerType::with_config(
+            Some(std::net::TcpListener::bind("127.0.0.1:0").unwrap()),
+            MemoryStorage::new(),
+            Arc::new(KeySubject::from_keypair(issuer_key(), None)),
+            Config::default(),
+        )
+        .unwrap();
+        let certificate: CertificateData = serde_json::from_value(json!({
+            "game_path_name": "Introduction to Rust",
+            "total_challenges": 10,
+            "solved_challenges": 8,
+            "performance_percentage": 80,
+            "profile_name": "Alice Rustacean",
+            "profile_id": "alice",
+            "date": "2024-09-30T08:00:00Z",
+        }))
+        .unwrap();
+        let holder = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp";
+        manager
+            .storage
+            .store_certificate("certificate-1".to_string(), certificate.clone());
+        let Some(CredentialResponseType::Immediate { credential, .. }) =
+            manager.storage.sign_certificate(
+                "token-1",
+                "certificate-1",
+                certificate,
+                holder.parse().unwrap(),
+                get_issuer_did(),
+                Arc::new(KeySubject::from_keypair(issuer_key(), None)),
+            )
+        else {
+            panic!("The certificate was not issued");
+        };
+        let session = PresentationSession {
+            id: "session-1".to_string(),
+            nonce: "nonce".to_string(),
+            min_performance: 60,
+            query_language: QueryLanguage::default(),
+            partner_id: None,
+            status: PresentationStatus::Pending,
+            created_at: Utc::now(),
+            expires_at: Utc::now() + Duration::minutes(5),
+            result: None,
+        };
+        let service = PresentationService::new(&manager);
+        let request = VerificationRequest::jwt_vc(credential.as_str().unwrap());
+        let errors =
+            |report: &VerificationReport| service.check_credential(&session, Some(holder), report);
+        let verifier = CredentialVerifier::offline().with_storage(manager.storage.clone());
+
+        let report = verifier.verify(&request).await;
+        assert_eq!(errors(&report), Vec::<String>::new());
+
+        manager.storage.revoke_certificate("certificate-1");
+        let report = verifier.verify(&request).await;
+        assert!(!errors(&report).is_empty());
+
+        // Without the issuer's storage there is nothing to vouch for the credential
+        let report = CredentialVerifier::offline().verify(&request).await;
+        assert!(!errors(&report).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::issuer_key::{issuer_key, key_id, sign_with_key};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use did_key::PatchedKeyPair;
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+
+/// JWT `typ` of the signed credential issuer metadata.
+const SIGNED_METADATA_TYPE: &str = "openidvci-issuer-metadata+jwt";
+
+lazy_static! {
+    // Fingerprint of metadata and key -> signed metadata
+    static ref SIGNED_METADATA: Mutex<Option<(String, String)>> = Mutex::new(None);
+}
+
+/// Signs the credential issuer metadata with the issuer key. The JWT is cached and signed
+/// again as soon as the metadata or the issuer key changes.
+pub fn signed_metadata(metadata: &Value) -> Result<String> {
+    sign_metadata(&issuer_key(), metadata)
+}
+
+fn sign_metadata(key: &PatchedKeyPair, metadata: &Value) -> Result<String> {
+    let credential_issuer = metadata["credential_issuer"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Metadata has no credential_issuer"))?;
+    let key_id = key_id(key);
+    let fingerprint = hex::encode(Sha256::digest(format!("{}{}", key_id, metadata)));
+
+    let mut cache = SIGNED_METADATA.lock().unwrap();
+    if let Some((cached_fingerprint, jwt)) = cache.as_ref() {
+        if *cached_fingerprint == fingerprint {
+            return Ok(jwt.clone());
+        }
+    }
+
+    log::info!("Signing credential issuer metadata with {}", key_id);
+    let mut claims = metadata.clone();
+    claims["iss"] = json!(credential_issuer);
+    claims["sub"] = json!(credential_issuer);
+    claims["iat"] = json!(Utc::now().timestamp());
+    let jwt = sign_with_key(key, SIGNED_METADATA_TYPE, &claims)?;
+    *cache = Some((fingerprint, jwt.clone()));
+    Ok(jwt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer_key::ed25519_key_from_secret;
+    use did_key::KeyMaterial;
+    use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+
+    #[test]
+    fn test_signed_metadata() {
+        let key = ed25519_key_from_secret("this-is-a-test-issuer-key");
+        let metadata = json!({
+            "credential_issuer": "http://localhost:3000",
+            "credential_endpoint": "http://localhost:3000/credential",
+        });
+
+        let jwt = sign_metadata(&key, &metadata).unwrap();
+        let header = decode_header(&jwt).unwrap();
+        assert_eq!(header.typ.as_deref(), Some(SIGNED_METADATA_TYPE));
+        assert_eq!(header.kid, Some(key_id(&key)));
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.required_spec_claims.clear();
+        let claims = decode::<Value>(
+            &jwt,
+            &DecodingKey::from_ed_der(&key.public_key_bytes()),
+            &validation,
+        )
+        .unwrap()
+        .claims;
+        assert_eq!(claims["iss"], "http://localhost:3000");
+        assert_eq!(claims["sub"], "http://localhost:3000");
+        assert_eq!(
+            claims["credential_endpoint"],
+            "http://localhost:3000/credential"
+        );
+
+        // Cached until the metadata or the key changes
+        assert_eq!(sign_metadata(&key, &metadata).unwrap(), jwt);
+        let mut changed = metadata.clone();
+        changed["credential_endpoint"] = json!("http://localhost:3000/v2/credential");
+        let changed_jwt = sign_metadata(&key, &changed).unwrap();
+        assert_ne!(changed_jwt, jwt);
+        let other_key = ed25519_key_from_secret("another-issuer-key");
+        let other_jwt = sign_metadata(&other_key, &changed).unwrap();
+        assert_ne!(other_jwt, changed_jwt);
+        assert!(decode::<Value>(
+            &other_jwt,
+            &DecodingKey::from_ed_der(&key.public_key_bytes()),
+            &validation,
+        )
+        .is_err());
+    }
+}
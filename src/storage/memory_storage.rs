@@ -1,6 +1,10 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
 use std::{collections::HashMap, fs::File};
 
-use crate::config::load_config;
+use crate::config::{
+    load_access_token_format, load_config, load_private_key, AccessTokenFormat, Config,
+};
 use did_key::{generate, DIDCore, Document, Ed25519KeyPair, PatchedKeyPair};
 use futures::executor::block_on;
 use jsonwebtoken::{Algorithm, Header};
@@ -19,6 +23,7 @@ use oid4vci::{
 };
 use reqwest::Url;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tracing::{debug, error, info, instrument};
 
@@ -33,16 +38,123 @@ fn generate_pre_authorized_code() -> PreAuthorizedCode {
     }
 }
 
-use crate::certificate_data::CertificateData;
+use super::snapshot::{merge_endorsements, merge_entries, StorageLock};
+use crate::access_token::{
+    decode_access_token, encode_access_token, is_jwt_access_token, AccessTokenClaims, Confirmation,
+    TokenIntrospection, ACCESS_TOKEN_TTL_SECONDS, OFFLINE_ACCESS_SCOPE, REFRESH_TOKEN_TTL_DAYS,
+};
+use crate::authorization_data::{
+    verify_pkce, AuthorizationCodeGrant, AuthorizationRequestQuery, AuthorizationSession,
+    FederatedLogin, AUTHORIZATION_CODE_TTL_MINUTES,
+};
+use crate::certificate_data::{CertificateData, CERTIFICATE_CREDENTIAL_CONFIGURATION_ID};
+use crate::clr_data::{clr_credential, ClrBundle, CLR_CREDENTIAL_CONFIGURATION_ID};
+use crate::credential_schema::{
+    credential_schema, validate_credential, CREDENTIAL_CONFIGURATION_FILES, CREDENTIAL_SCHEMA_FIELD,
+};
+use crate::deferred_data::{DeferredCredentialErrorResponse, PendingReview, ReviewStatus};
+use crate::display::load_display_config;
+use crate::dpop::{access_token_hash, DPOP_PROOF_MAX_AGE_SECONDS, DPOP_TOKEN_TYPE};
+use crate::endorsement_data::{embed_endorsements, Endorsement};
+use crate::notification_data::{CredentialNotification, NotificationRequest};
+use crate::presentation_data::{PresentationResult, PresentationSession, PresentationStatus};
+use crate::public_verification::{public_credential_id, PublicCredential, PublicVerification};
+use crate::storage::StorageSnapshot;
+use crate::webhooks::{WebhookDispatcher, WebhookEvent, WebhookEventType};
+use chrono::{DateTime, Duration, Utc};
 use std::sync::{Arc, Mutex};
-use types_ob_v3::prelude::AchievementCredential;
 use uuid::Uuid;
 
+pub const OFFER_TTL_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct PreAuthorizedOffer {
+    certificate_ids: Vec<String>,
+    expires_at: DateTime<Utc>,
+    #[serde(default)]
+    redeemed_at: Option<DateTime<Utc>>, // set once the pre-authorized code was exchanged for a token
+}
+
+/// Certificates that can be claimed with an access token, issued in order unless the wallet
+/// names them by their credential identifier.
+#[derive(Clone, Debug)]
+struct AccessGrant {
+    certificate_ids: Vec<String>,
+    claimed: Vec<bool>, // one flag per certificate id, set once its credential was requested
+    dpop_jkt: Option<String>, // thumbprint of the wallet key the token is bound to
+    expires_at: DateTime<Utc>,
+}
+
+/// Certificates already claimed with a JWT access token, kept in the storage file so instances
+/// sharing it issue each certificate once.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct AccessTokenClaim {
+    claimed: Vec<bool>,
+    expires_at: DateTime<Utc>,
+    #[serde(default)]
+    revoked: bool, // set when the refresh token family the token was issued with is revoked
+}
+
+/// A refresh token, each one can only be used once and is rotated into a new token of the same family.
+#[derive(Clone, Debug)]
+struct RefreshGrant {
+    family_id: String,
+    certificate_ids: Vec<String>,
+    dpop_jkt: Option<String>,
+    expires_at: DateTime<Utc>,
+    used: bool,
+}
+
+/// An access token issued with a refresh token family, revoked with the family when one of
+/// its refresh tokens is reused.
+#[derive(Clone, Debug)]
+enum FamilyAccessToken {
+    Opaque(String),
+    Jwt {
+        jti: String,
+        expires_at: DateTime<Utc>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct IssuedCredential {
+    certificate_id: String,
+    access_token: String,
+}
+
 #[derive(Clone)]
 pub struct MemoryStorage {
     certificates: Arc<Mutex<HashMap<String, CertificateData>>>,
-    pre_authorized_codes: Arc<Mutex<HashMap<String, String>>>, // pre-authorized code -> certificate id
-    access_tokens: Arc<Mutex<HashMap<String, String>>>,        // access token -> certificate id
+    pre_authorized_codes: Arc<Mutex<HashMap<String, PreAuthorizedOffer>>>, // pre-authorized code -> offer
+    access_tokens: Arc<Mutex<HashMap<String, AccessGrant>>>, // access token -> granted certificates
+    access_token_claims: Arc<Mutex<HashMap<String, AccessTokenClaim>>>, // JWT access token jti -> claimed certificates
+    revoked_certificates: Arc<Mutex<HashMap<String, DateTime<Utc>>>>, // certificate id -> revocation date
+    notification_ids: Arc<Mutex<HashMap<String, IssuedCredential>>>, // notification id -> issued credential
+    notifications: Arc<Mutex<HashMap<String, Vec<CredentialNotification>>>>, // certificate id -> notifications
+    reviews: Arc<Mutex<HashMap<String, PendingReview>>>, // transaction id -> review
+    issuer_states: Arc<Mutex<HashMap<String, Vec<String>>>>, // issuer state -> certificate ids
+    authorization_sessions: Arc<Mutex<HashMap<String, AuthorizationSession>>>, // session id -> session
+    authorization_codes: Arc<Mutex<HashMap<String, AuthorizationCodeGrant>>>, // authorization code -> grant
+    dpop_proofs: Arc<Mutex<HashMap<String, DateTime<Utc>>>>, // DPoP proof jti -> first use
+    refresh_tokens: Arc<Mutex<HashMap<String, RefreshGrant>>>, // refresh token -> grant
+    token_families: Arc<Mutex<HashMap<String, Vec<FamilyAccessToken>>>>, // refresh token family id -> access tokens
+    clr_bundles: Arc<Mutex<HashMap<String, ClrBundle>>>, // CLR id -> bundled certificates
+    endorsements: Arc<Mutex<Vec<Endorsement>>>,
+    presentation_sessions: Arc<Mutex<HashMap<String, PresentationSession>>>, // state -> session
+    public_credentials: Arc<Mutex<HashMap<String, PublicCredential>>>, // public credential id -> certificate
+    verification_opt_outs: Arc<Mutex<HashMap<String, DateTime<Utc>>>>, // profile id -> opt-out date
+    webhooks: Option<WebhookDispatcher>,
+    config: Option<Config>, // issuer key and URL for access tokens, read from the environment if not set
+    storage_file: Option<PathBuf>,
+    synced: Arc<Mutex<SyncedFile>>, // storage file as last loaded or saved
+}
+
+/// Content and modification time of the storage file when it was last loaded or saved, the base
+/// that changes of this storage and of other processes are merged on.
+#[derive(Default)]
+struct SyncedFile {
+    modified_at: Option<SystemTime>,
+    snapshot: StorageSnapshot,
 }
 
 impl MemoryStorage {
@@ -51,14 +163,236 @@ impl MemoryStorage {
             certificates: Arc::new(Mutex::new(HashMap::new())),
             pre_authorized_codes: Arc::new(Mutex::new(HashMap::new())),
             access_tokens: Arc::new(Mutex::new(HashMap::new())),
+            access_token_claims: Arc::new(Mutex::new(HashMap::new())),
+            revoked_certificates: Arc::new(Mutex::new(HashMap::new())),
+            notification_ids: Arc::new(Mutex::new(HashMap::new())),
+            notifications: Arc::new(Mutex::new(HashMap::new())),
+            reviews: Arc::new(Mutex::new(HashMap::new())),
+            issuer_states: Arc::new(Mutex::new(HashMap::new())),
+            authorization_sessions: Arc::new(Mutex::new(HashMap::new())),
+            authorization_codes: Arc::new(Mutex::new(HashMap::new())),
+            dpop_proofs: Arc::new(Mutex::new(HashMap::new())),
+            refresh_tokens: Arc::new(Mutex::new(HashMap::new())),
+            token_families: Arc::new(Mutex::new(HashMap::new())),
+            clr_bundles: Arc::new(Mutex::new(HashMap::new())),
+            endorsements: Arc::new(Mutex::new(vec![])),
+            presentation_sessions: Arc::new(Mutex::new(HashMap::new())),
+            public_credentials: Arc::new(Mutex::new(HashMap::new())),
+            verification_opt_outs: Arc::new(Mutex::new(HashMap::new())),
+            webhooks: None,
+            config: None,
+            storage_file: None,
+            synced: Arc::new(Mutex::new(SyncedFile::default())),
+        }
+    }
+
+    /// Keeps certificates, offers and revocations in a JSON file, loading what it already holds.
+    pub fn with_storage_file(mut self, storage_file: PathBuf) -> anyhow::Result<Self> {
+        let snapshot = StorageSnapshot::load(&storage_file)?;
+        log::info!(
+            "Loaded {} certificates from {}",
+            snapshot.certificates.len(),
+            storage_file.display()
+        );
+        {
+            let mut synced = self.synced.lock().unwrap();
+            self.apply(&synced.snapshot, snapshot.clone());
+            *synced = SyncedFile {
+                modified_at: modified_at(&storage_file),
+                snapshot,
+            };
+        }
+        self.storage_file = Some(storage_file);
+        Ok(self)
+    }
+
+    pub fn storage_file(&self) -> Option<&PathBuf> {
+        self.storage_file.as_ref()
+    }
+
+    fn snapshot(&self) -> StorageSnapshot {
+        StorageSnapshot {
+            certificates: self.certificates.lock().unwrap().clone(),
+            pre_authorized_codes: self.pre_authorized_codes.lock().unwrap().clone(),
+            issuer_states: self.issuer_states.lock().unwrap().clone(),
+            revoked_certificates: self.revoked_certificates.lock().unwrap().clone(),
+            clr_bundles: self.clr_bundles.lock().unwrap().clone(),
+            endorsements: self.endorsements.lock().unwrap().clone(),
+            public_credentials: self.public_credentials.lock().unwrap().clone(),
+            verification_opt_outs: self.verification_opt_outs.lock().unwrap().clone(),
+            access_token_claims: self.access_token_claims.lock().unwrap().clone(),
+            reviews: self.reviews.lock().unwrap().clone(),
+        }
+    }
+
+    /// Takes over what changed in the file since `base`, keeping the changes this storage made
+    /// since then, including removals.
+    fn apply(&self, base: &StorageSnapshot, theirs: StorageSnapshot) {
+        fn apply_map<T: Serialize + Clone>(
+            map: &Mutex<HashMap<String, T>>,
+            base: &HashMap<String, T>,
+            theirs: HashMap<String, T>,
+        ) {
+            let mut map = map.lock().unwrap();
+            *map = merge_entries(base, &map, theirs);
+        }
+        apply_map(&self.certificates, &base.certificates, theirs.certificates);
+        apply_map(
+            &self.pre_authorized_codes,
+            &base.pre_authorized_codes,
+            theirs.pre_authorized_codes,
+        );
+        apply_map(
+            &self.issuer_states,
+            &base.issuer_states,
+            theirs.issuer_states,
+        );
+        apply_map(
+            &self.revoked_certificates,
+            &base.revoked_certificates,
+            theirs.revoked_certificates,
+        );
+        apply_map(&self.clr_bundles, &base.clr_bundles, theirs.clr_bundles);
+        apply_map(
+            &self.public_credentials,
+            &base.public_credentials,
+            theirs.public_credentials,
+        );
+        apply_map(
+            &self.verification_opt_outs,
+            &base.verification_opt_outs,
+            theirs.verification_opt_outs,
+        );
+        apply_map(
+            &self.access_token_claims,
+            &base.access_token_claims,
+            theirs.access_token_claims,
+        );
+        apply_map(&self.reviews, &base.reviews, theirs.reviews);
+        let mut endorsements = self.endorsements.lock().unwrap();
+        *endorsements = merge_endorsements(&base.endorsements, &endorsements, theirs.endorsements);
+    }
+
+    /// Saves the storage to its file, if it has one. Changes other processes saved in the
+    /// meantime are merged in first, while holding the file's lock.
+    fn persist(&self) {
+        self.synchronized(|| ((), true))
+    }
+
+    /// Runs `f` while holding the storage file's lock, after merging in the changes other
+    /// processes saved, and saves the storage if `f` reports a change. Without a storage file
+    /// `f` just runs.
+    fn synchronized<T>(&self, f: impl FnOnce() -> (T, bool)) -> T {
+        let Some(storage_file) = &self.storage_file else {
+            return f().0;
+        };
+        let mut synced = self.synced.lock().unwrap();
+        let lock = StorageLock::acquire(storage_file).and_then(|lock| {
+            let snapshot = StorageSnapshot::load(storage_file)?;
+            self.apply(&synced.snapshot, snapshot.clone());
+            *synced = SyncedFile {
+                modified_at: modified_at(storage_file),
+                snapshot,
+            };
+            Ok(lock)
+        });
+        let (result, changed) = f();
+        let saved = lock.and_then(|_lock| {
+            if changed {
+                let snapshot = self.snapshot();
+                snapshot.save(storage_file)?;
+                *synced = SyncedFile {
+                    modified_at: modified_at(storage_file),
+                    snapshot,
+                };
+            }
+            Ok(())
+        });
+        if let Err(e) = saved {
+            log::error!("Failed to save {}: {}", storage_file.display(), e);
+        }
+        result
+    }
+
+    /// Picks up changes others made to the storage file, e.g. with the command line.
+    #[instrument(skip_all)]
+    pub fn reload(&self) -> anyhow::Result<bool> {
+        let Some(storage_file) = &self.storage_file else {
+            return Ok(false);
+        };
+        let mut synced = self.synced.lock().unwrap();
+        let modified = modified_at(storage_file);
+        if modified.is_none() || modified <= synced.modified_at {
+            return Ok(false);
+        }
+        let snapshot = StorageSnapshot::load(storage_file)?;
+        self.apply(&synced.snapshot, snapshot.clone());
+        *synced = SyncedFile {
+            modified_at: modified,
+            snapshot,
+        };
+        log::info!("Reloaded {}", storage_file.display());
+        Ok(true)
+    }
+
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Private key and URL of the issuer, which sign and validate JWT access tokens.
+    fn issuer_config(&self) -> (String, String) {
+        match &self.config {
+            Some(Config {
+                private_key,
+                issuer_url: Some(issuer_url),
+            }) => (private_key.clone(), issuer_url.clone()),
+            _ => load_config(),
+        }
+    }
+
+    fn decode_access_token(&self, access_token: &str) -> anyhow::Result<AccessTokenClaims> {
+        let (private_key, issuer_url) = self.issuer_config();
+        decode_access_token(access_token, &private_key, &issuer_url)
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn webhooks(&self) -> Option<&WebhookDispatcher> {
+        self.webhooks.as_ref()
+    }
+
+    pub fn notify(&self, event_type: WebhookEventType, data: serde_json::Value) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(WebhookEvent::new(event_type, data));
         }
     }
 
     #[instrument(skip_all)]
     pub fn store_certificate(&self, certificate_id: String, certificate_data: CertificateData) {
         log::info!("Storing certificate with id: {}", certificate_id);
-        let mut certificates = self.certificates.lock().unwrap();
-        certificates.insert(certificate_id, certificate_data);
+        {
+            let mut certificates = self.certificates.lock().unwrap();
+            certificates.insert(certificate_id, certificate_data);
+        }
+        self.persist();
+    }
+
+    /// All stored certificates by their id, sorted by date.
+    #[instrument(skip_all)]
+    pub fn list_certificates(&self) -> Vec<(String, CertificateData)> {
+        let mut certificates: Vec<(String, CertificateData)> = {
+            let certificates = self.certificates.lock().unwrap();
+            certificates
+                .iter()
+                .map(|(certificate_id, certificate)| (certificate_id.clone(), certificate.clone()))
+                .collect()
+        };
+        certificates.sort_by_key(|(_, certificate)| certificate.date);
+        certificates
     }
     #[instrument(skip_all)]
     pub fn get_certificate(&self, certificate_id: &str) -> Option<CertificateData> {
@@ -70,224 +404,2059 @@ impl MemoryStorage {
     pub fn associate_pre_authorized_code(
         &self,
         pre_authorized_code: String,
-        certificate_id: String,
+        certificate_ids: Vec<String>,
     ) {
         log::info!(
-            "Associating pre-authorized code {} with certificate id: {}",
+            "Associating pre-authorized code {} with certificate ids: {:?}",
             pre_authorized_code,
-            certificate_id
+            certificate_ids
         );
-        let mut codes = self.pre_authorized_codes.lock().unwrap();
-        codes.insert(pre_authorized_code, certificate_id);
+        {
+            let mut codes = self.pre_authorized_codes.lock().unwrap();
+            codes.insert(
+                pre_authorized_code,
+                PreAuthorizedOffer {
+                    certificate_ids,
+                    expires_at: Utc::now() + Duration::hours(OFFER_TTL_HOURS),
+                    redeemed_at: None,
+                },
+            );
+        }
+        self.persist();
     }
+    /// Associates an access token with the certificates it grants, optionally bound to the
+    /// key thumbprint of the DPoP proof it was requested with.
     #[instrument(skip_all)]
-    pub fn associate_access_token(&self, access_token: String, certificate_id: String) {
+    pub fn associate_access_token(
+        &self,
+        access_token: String,
+        certificate_ids: Vec<String>,
+        dpop_jkt: Option<String>,
+    ) {
         log::info!(
-            "Associating access token {} with certificate id: {}",
+            "Associating access token {} with certificate ids: {:?}",
             access_token,
-            certificate_id
+            certificate_ids
         );
         let mut tokens = self.access_tokens.lock().unwrap();
-        tokens.insert(access_token, certificate_id);
+        tokens.insert(
+            access_token,
+            AccessGrant {
+                claimed: vec![false; certificate_ids.len()],
+                certificate_ids,
+                dpop_jkt,
+                expires_at: Utc::now() + Duration::seconds(ACCESS_TOKEN_TTL_SECONDS),
+            },
+        );
     }
-    #[instrument(skip_all)]
-    pub fn get_certificate_id_by_pre_authorized_code(
+    /// Grant of an access token. Opaque tokens are looked up, JWT access tokens are validated
+    /// on every use and combined with the certificates already claimed with them.
+    fn access_grant(&self, access_token: &str) -> Option<AccessGrant> {
+        if is_jwt_access_token(access_token) {
+            let claims = self
+                .decode_access_token(access_token)
+                .map_err(|e| log::warn!("Invalid access token: {}", e))
+                .ok()?;
+            return self.jwt_access_grant(&claims);
+        }
+        let tokens = self.access_tokens.lock().unwrap();
+        tokens
+            .get(access_token)
+            .filter(|grant| grant.expires_at > Utc::now())
+            .cloned()
+    }
+
+    /// Grant of a valid JWT access token, `None` if it was revoked with its refresh token family.
+    fn jwt_access_grant(&self, claims: &AccessTokenClaims) -> Option<AccessGrant> {
+        let claim = self
+            .access_token_claims
+            .lock()
+            .unwrap()
+            .get(&claims.jti)
+            .cloned();
+        if claim.as_ref().map_or(false, |claim| claim.revoked) {
+            log::warn!("Access token {} has been revoked", claims.jti);
+            return None;
+        }
+        Some(AccessGrant {
+            claimed: claim
+                .map(|claim| claim.claimed)
+                .unwrap_or_else(|| vec![false; claims.certificate_ids.len()]),
+            certificate_ids: claims.certificate_ids.clone(),
+            dpop_jkt: claims.cnf.clone().map(|cnf| cnf.jkt),
+            expires_at: DateTime::from_timestamp(claims.exp, 0).unwrap_or_default(),
+        })
+    }
+
+    /// Runs `f` on the grant of an access token to claim its certificates. Claims of JWT access
+    /// tokens are made under the storage file's lock and saved to it, so instances sharing the
+    /// file do not issue a certificate twice.
+    fn update_access_grant<T>(
         &self,
-        pre_authorized_code: &str,
-    ) -> Option<String> {
-        let codes = self.pre_authorized_codes.lock().unwrap();
-        codes.get(pre_authorized_code).cloned()
+        access_token: &str,
+        f: impl FnOnce(&mut AccessGrant) -> T,
+    ) -> Option<T> {
+        if !is_jwt_access_token(access_token) {
+            let mut tokens = self.access_tokens.lock().unwrap();
+            return tokens
+                .get_mut(access_token)
+                .filter(|grant| grant.expires_at > Utc::now())
+                .map(f);
+        }
+        let claims = self
+            .decode_access_token(access_token)
+            .map_err(|e| log::warn!("Invalid access token: {}", e))
+            .ok()?;
+        self.synchronized(|| {
+            let Some(mut grant) = self.jwt_access_grant(&claims) else {
+                return (None, false);
+            };
+            let claimed = grant.claimed.clone();
+            let result = f(&mut grant);
+            let changed = grant.claimed != claimed;
+            if changed {
+                self.access_token_claims.lock().unwrap().insert(
+                    claims.jti.clone(),
+                    AccessTokenClaim {
+                        claimed: grant.claimed,
+                        expires_at: grant.expires_at,
+                        revoked: false,
+                    },
+                );
+            }
+            (Some(result), changed)
+        })
     }
     #[instrument(skip_all)]
-    pub fn get_certificate_id_by_access_token(&self, access_token: &str) -> Option<String> {
-        let tokens = self.access_tokens.lock().unwrap();
-        tokens.get(access_token).cloned()
+    pub fn get_access_token_jkt(&self, access_token: &str) -> Option<String> {
+        self.access_grant(access_token)
+            .and_then(|grant| grant.dpop_jkt)
     }
-}
-
-impl<CFC: CredentialFormatCollection + DeserializeOwned> Storage<CFC> for MemoryStorage {
+    /// Describes an access token for the introspection endpoint (RFC 7662).
     #[instrument(skip_all)]
-    fn get_credential_configurations_supported(
-        &self,
-    ) -> HashMap<String, CredentialConfigurationsSupportedObject<CFC>> {
-        log::debug!("get_credential_configurations_supported");
-        vec![(
-            "KonnektorenCertificate".to_string(),
-            serde_json::from_reader(
-                File::open("./assets/konnektoren_certificate_config.json").unwrap(),
-            )
-            .unwrap(),
-        )]
-        .into_iter()
-        .collect()
+    pub fn introspect_access_token(&self, access_token: &str) -> TokenIntrospection {
+        let claims = if is_jwt_access_token(access_token) {
+            self.decode_access_token(access_token).ok()
+        } else {
+            None
+        };
+        match self.access_grant(access_token) {
+            Some(grant) => TokenIntrospection {
+                active: true,
+                token_type: Some(
+                    match grant.dpop_jkt {
+                        Some(_) => DPOP_TOKEN_TYPE,
+                        None => "bearer",
+                    }
+                    .to_string(),
+                ),
+                certificate_ids: grant.certificate_ids,
+                claimed: Some(grant.claimed.iter().filter(|claimed| **claimed).count()),
+                cnf: grant.dpop_jkt.map(|jkt| Confirmation { jkt }),
+                iss: claims.as_ref().map(|claims| claims.iss.clone()),
+                aud: claims.as_ref().map(|claims| claims.aud.clone()),
+                jti: claims.as_ref().map(|claims| claims.jti.clone()),
+                iat: claims.as_ref().map(|claims| claims.iat),
+                exp: claims.as_ref().map(|claims| claims.exp),
+            },
+            None => TokenIntrospection::inactive(),
+        }
     }
-
+    /// Records the `jti` of a DPoP proof, returns `false` if the proof was already used.
     #[instrument(skip_all)]
-    fn get_authorization_code(&self) -> Option<AuthorizationCode> {
-        let state = Uuid::new_v4().to_string();
-        log::debug!("get_authorization_code {}", state);
-        Some(AuthorizationCode {
-            issuer_state: Some(state),
-            authorization_server: None,
-        })
+    pub fn record_dpop_proof(&self, jti: &str) -> bool {
+        let mut proofs = self.dpop_proofs.lock().unwrap();
+        let now = Utc::now();
+        proofs.retain(|_, used_at| {
+            *used_at + Duration::seconds(2 * DPOP_PROOF_MAX_AGE_SECONDS) > now
+        });
+        if proofs.contains_key(jti) {
+            return false;
+        }
+        proofs.insert(jti.to_string(), now);
+        true
     }
-
+    /// Exchanges a pre-authorized code for the certificate ids of its offer.
+    /// The code can only be redeemed once and only before the offer expires.
     #[instrument(skip_all)]
-    fn get_authorization_response(&self) -> Option<AuthorizationResponse> {
-        let code = generate_authorization_code(16);
+    pub fn redeem_pre_authorized_code(&self, pre_authorized_code: &str) -> Option<Vec<String>> {
+        let certificate_ids = {
+            let mut codes = self.pre_authorized_codes.lock().unwrap();
+            let offer = codes
+                .get_mut(pre_authorized_code)
+                .filter(|offer| offer.redeemed_at.is_none() && offer.expires_at > Utc::now())?;
+            offer.redeemed_at = Some(Utc::now());
+            offer.certificate_ids.clone()
+        };
+        self.persist();
+        Some(certificate_ids)
+    }
+    #[instrument(skip_all)]
+    pub fn get_certificate_ids_by_access_token(&self, access_token: &str) -> Option<Vec<String>> {
+        self.access_grant(access_token)
+            .map(|grant| grant.certificate_ids)
+    }
+    /// Returns the next certificate to issue for the access token. Each credential request,
+    /// single or as part of a batch, is bound to its own certificate of the grant. Once all
+    /// certificates were issued, further requests are refused.
+    #[instrument(skip_all)]
+    pub fn claim_certificate_id(&self, access_token: &str) -> Option<String> {
+        self.claim_certificate_ids(access_token, 1)
+            .and_then(|certificate_ids| certificate_ids.into_iter().next())
+    }
 
-        Some(AuthorizationResponse { code, state: None })
+    /// Claims the next `count` certificates of the access token's grant at once, or none if
+    /// fewer are left.
+    #[instrument(skip_all)]
+    pub fn claim_certificate_ids(&self, access_token: &str, count: usize) -> Option<Vec<String>> {
+        self.update_access_grant(access_token, |grant| {
+            let unclaimed: Vec<usize> = (0..grant.certificate_ids.len())
+                .filter(|index| !grant.claimed[*index])
+                .collect();
+            if count > unclaimed.len() {
+                log::warn!(
+                    "Requested {} more credentials, but only {} of {} are left",
+                    count,
+                    unclaimed.len(),
+                    grant.certificate_ids.len()
+                );
+                return None;
+            }
+            let certificate_ids = unclaimed[..count]
+                .iter()
+                .map(|index| {
+                    grant.claimed[*index] = true;
+                    grant.certificate_ids[*index].clone()
+                })
+                .collect();
+            Some(certificate_ids)
+        })
+        .flatten()
     }
 
+    /// Claims the certificate a wallet named by its credential identifier, returns `false` if it
+    /// is not part of the access token's grant or its credential was already requested.
     #[instrument(skip_all)]
-    fn get_pre_authorized_code(&self) -> Option<PreAuthorizedCode> {
-        Some(generate_pre_authorized_code())
+    pub fn claim_certificate_identifier(&self, access_token: &str, certificate_id: &str) -> bool {
+        self.update_access_grant(access_token, |grant| {
+            let index = (0..grant.certificate_ids.len()).find(|index| {
+                !grant.claimed[*index] && grant.certificate_ids[*index] == certificate_id
+            });
+            match index {
+                Some(index) => {
+                    grant.claimed[index] = true;
+                    true
+                }
+                None => {
+                    log::warn!(
+                        "Credential identifier {} is not granted or already claimed",
+                        certificate_id
+                    );
+                    false
+                }
+            }
+        })
+        .unwrap_or(false)
     }
 
+    /// Grants an access token for the token request. Authorization codes are only redeemed for
+    /// the `client_id` they were issued to. Tokens requested with a DPoP proof are bound
+    /// to the key thumbprint `dpop_jkt` and returned with the `DPoP` token type. With
+    /// `offline_access` a refresh token is issued as well.
     #[instrument(skip_all)]
-    fn get_token_response(&self, token_request: TokenRequest) -> Option<TokenResponse> {
+    pub fn get_bound_token_response(
+        &self,
+        token_request: TokenRequest,
+        client_id: Option<&str>,
+        dpop_jkt: Option<String>,
+        offline_access: bool,
+    ) -> Option<TokenResponse> {
         log::debug!("get_token_response: {:?}", token_request);
-        let (is_valid, pre_authorized_code) = match token_request {
-            TokenRequest::AuthorizationCode { code, .. } => (
-                self.get_certificate_id_by_access_token(&code).is_some(),
-                None,
+        let certificate_ids = match token_request {
+            TokenRequest::AuthorizationCode {
+                code,
+                code_verifier,
+                redirect_uri,
+            } => self.redeem_authorization_code(
+                &code,
+                client_id,
+                code_verifier.as_deref(),
+                redirect_uri.as_deref(),
             ),
             TokenRequest::PreAuthorizedCode {
                 pre_authorized_code,
                 ..
-            } => (
-                self.get_certificate_id_by_pre_authorized_code(&pre_authorized_code)
-                    .is_some(),
-                Some(pre_authorized_code),
-            ),
+            } => self.redeem_pre_authorized_code(&pre_authorized_code),
         };
 
-        if is_valid {
-            let access_token = generate_authorization_code(16); // Generate a new access token
-            if let Some(pre_authorized_code) = pre_authorized_code {
-                if let Some(certificate_id) =
-                    self.get_certificate_id_by_pre_authorized_code(&pre_authorized_code)
-                {
-                    self.associate_access_token(access_token.clone(), certificate_id);
-                }
+        match certificate_ids {
+            Some(certificate_ids) => {
+                let family_id = offline_access.then(|| Uuid::new_v4().to_string());
+                self.issue_token_response(certificate_ids, dpop_jkt, family_id)
+            }
+            None => {
+                log::warn!("Token request was not granted");
+                None
             }
-
-            Some(TokenResponse {
-                access_token,
-                token_type: "bearer".to_string(),
-                expires_in: Some(86400),
-                refresh_token: None,
-                scope: None,
-                c_nonce: Some(C_NONCE.clone()),
-                c_nonce_expires_in: Some(86400),
-            })
-        } else {
-            None
         }
     }
 
+    /// Exchanges a refresh token for a new access token to the same certificates. The refresh
+    /// token is rotated, presenting an already used token revokes its whole family along with the
+    /// access tokens issued with it. Grants that are not bound yet are bound to the key of a DPoP
+    /// proof presented with the refresh.
     #[instrument(skip_all)]
-    fn get_credential_response(
+    pub fn refresh_token_response(
         &self,
-        access_token: String,
-        subject_did: Url,
-        issuer_did: Url,
-        credential_format: CFC,
-        signer: SigningSubject,
-    ) -> Option<CredentialResponse> {
-        log::debug!("Getting credential response for {}", access_token);
-
-        log::debug!("access_token: {}", access_token);
-        log::debug!("credential_format: {:?}", credential_format);
-        log::debug!("subject did: {}", subject_did);
-        log::debug!("issuer did: {}", issuer_did);
+        refresh_token: &str,
+        dpop_jkt: Option<String>,
+    ) -> Option<TokenResponse> {
+        let grant = {
+            let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+            let grant = refresh_tokens.get_mut(refresh_token)?;
+            if grant.used {
+                let family_id = grant.family_id.clone();
+                log::warn!(
+                    "Refresh token reuse detected, revoking token family {}",
+                    family_id
+                );
+                refresh_tokens.retain(|_, grant| grant.family_id != family_id);
+                drop(refresh_tokens);
+                self.revoke_token_family(&family_id);
+                return None;
+            }
+            if grant.expires_at <= Utc::now() {
+                log::warn!("Refresh token expired at {}", grant.expires_at);
+                return None;
+            }
+            // Refresh tokens of DPoP-bound grants may only be used with the same key
+            if grant.dpop_jkt.is_some() && grant.dpop_jkt != dpop_jkt {
+                log::warn!("Refresh token is bound to another key");
+                return None;
+            }
+            grant.used = true;
+            let mut grant = grant.clone();
+            grant.dpop_jkt = grant.dpop_jkt.or(dpop_jkt);
+            grant
+        };
 
-        let issuer_did = get_issuer_did();
-        log::debug!("updated issuer did {}", issuer_did);
+        self.issue_token_response(grant.certificate_ids, grant.dpop_jkt, Some(grant.family_id))
+    }
 
-        let type_ = match serde_json::from_value::<CredentialFormats<WithParameters>>(
-            serde_json::to_value(credential_format).unwrap(),
-        )
-        .unwrap()
+    /// Revokes the access tokens issued with a refresh token family. Revoked JWT access tokens
+    /// are recorded in the storage file, so no instance sharing it accepts them anymore.
+    fn revoke_token_family(&self, family_id: &str) {
+        let family = self
+            .token_families
+            .lock()
+            .unwrap()
+            .remove(family_id)
+            .unwrap_or_default();
+        let mut revoked_jwts = Vec::new();
         {
-            CredentialFormats::JwtVcJson(credential) => {
-                credential.parameters.credential_definition.type_
+            let mut tokens = self.access_tokens.lock().unwrap();
+            for access_token in family {
+                match access_token {
+                    FamilyAccessToken::Opaque(access_token) => {
+                        tokens.remove(&access_token);
+                    }
+                    FamilyAccessToken::Jwt { jti, expires_at } => {
+                        revoked_jwts.push((jti, expires_at))
+                    }
+                }
             }
-            _ => unreachable!("Credential format not supported"),
-        };
-
-        let certificate_id = self.get_certificate_id_by_access_token(&access_token);
-        log::debug!("certificate_id: {:?}", certificate_id);
+        }
+        if revoked_jwts.is_empty() {
+            return;
+        }
+        self.synchronized(|| {
+            let mut claims = self.access_token_claims.lock().unwrap();
+            for (jti, expires_at) in revoked_jwts {
+                let claim = claims.entry(jti).or_insert(AccessTokenClaim {
+                    claimed: Vec::new(),
+                    expires_at,
+                    revoked: false,
+                });
+                claim.revoked = true;
+            }
+            ((), true)
+        })
+    }
 
-        let certificate = certificate_id
-            .clone()
-            .and_then(|id| self.get_certificate(&id));
+    fn issue_refresh_token(
+        &self,
+        family_id: String,
+        certificate_ids: Vec<String>,
+        dpop_jkt: Option<String>,
+    ) -> String {
+        let refresh_token = generate_authorization_code(32);
+        let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+        refresh_tokens.insert(
+            refresh_token.clone(),
+            RefreshGrant {
+                family_id,
+                certificate_ids,
+                dpop_jkt,
+                expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+                used: false,
+            },
+        );
+        refresh_token
+    }
 
-        let credential_json: serde_json::Value = match certificate {
-            Some(certificate) => {
-                let achievement_credential: AchievementCredential = certificate.into();
-                serde_json::to_value(achievement_credential).unwrap()
+    /// Issues an access token to the certificates. With a refresh token family a refresh token
+    /// is issued as well, and the access token is revoked with the family.
+    fn issue_token_response(
+        &self,
+        certificate_ids: Vec<String>,
+        dpop_jkt: Option<String>,
+        family_id: Option<String>,
+    ) -> Option<TokenResponse> {
+        let (access_token, family_access_token) = match load_access_token_format() {
+            AccessTokenFormat::Opaque => {
+                let access_token = generate_authorization_code(16); // Generate a new access token
+                self.associate_access_token(
+                    access_token.clone(),
+                    certificate_ids.clone(),
+                    dpop_jkt.clone(),
+                );
+                (
+                    access_token.clone(),
+                    FamilyAccessToken::Opaque(access_token),
+                )
             }
-            None => {
-                log::error!(
-                    "Certificate not found for certificate id: {:?}",
-                    certificate_id
+            AccessTokenFormat::Jwt => {
+                let (private_key, issuer_url) = self.issuer_config();
+                let claims = AccessTokenClaims::new(
+                    &issuer_url,
+                    certificate_ids.clone(),
+                    &C_NONCE,
+                    dpop_jkt.clone(),
                 );
-                return None;
+                let access_token = encode_access_token(&private_key, &claims)
+                    .map_err(|e| log::error!("Failed to sign access token: {}", e))
+                    .ok()?;
+                (
+                    access_token,
+                    FamilyAccessToken::Jwt {
+                        jti: claims.jti,
+                        expires_at: DateTime::from_timestamp(claims.exp, 0).unwrap_or_default(),
+                    },
+                )
             }
         };
-
-        log::debug!("Credential JSON: {:?}", credential_json);
-
-        let mut verifiable_credential: serde_json::Value = credential_json;
-        verifiable_credential["issuer"] = json!(issuer_did);
-        verifiable_credential["credentialSubject"]["id"] = json!(subject_did);
-
-        log::debug!("Verifiable Credential: {:?}", verifiable_credential);
-
-        Some(CredentialResponse {
-            credential: CredentialResponseType::Immediate {
-                credential: serde_json::to_value(block_on(async {
-                    jwt::encode(
-                        signer.clone(),
-                        Header::new(Algorithm::EdDSA),
-                        VerifiableCredentialJwt::builder()
-                            .sub(subject_did.clone())
-                            .iss(issuer_did.clone())
-                            .iat(0)
-                            .exp(9999999999i64)
-                            .verifiable_credential(verifiable_credential)
-                            .build()
-                            .ok(),
-                        "did:key",
-                    )
-                    .await
-                    .ok()
-                }))
-                .unwrap(),
-                notification_id: None,
+        let refresh_token = family_id.map(|family_id| {
+            self.token_families
+                .lock()
+                .unwrap()
+                .entry(family_id.clone())
+                .or_default()
+                .push(family_access_token);
+            self.issue_refresh_token(family_id, certificate_ids.clone(), dpop_jkt.clone())
+        });
+        self.notify(
+            WebhookEventType::TokenIssued,
+            json!({ "certificate_ids": certificate_ids }),
+        );
+        Some(TokenResponse {
+            access_token,
+            token_type: match dpop_jkt {
+                Some(_) => DPOP_TOKEN_TYPE.to_string(),
+                None => "bearer".to_string(),
             },
+            expires_in: Some(86400),
+            scope: refresh_token
+                .as_ref()
+                .map(|_| OFFLINE_ACCESS_SCOPE.to_string()),
+            refresh_token,
             c_nonce: Some(C_NONCE.clone()),
             c_nonce_expires_in: Some(86400),
         })
     }
 
-    fn get_state(&self) -> Option<String> {
-        log::debug!("Getting state in memory storage");
-        None
+    #[instrument(skip_all)]
+    pub fn associate_issuer_state(&self, issuer_state: String, certificate_ids: Vec<String>) {
+        log::info!(
+            "Associating issuer state {} with certificate ids: {:?}",
+            issuer_state,
+            certificate_ids
+        );
+        {
+            let mut issuer_states = self.issuer_states.lock().unwrap();
+            issuer_states.insert(issuer_state, certificate_ids);
+        }
+        self.persist();
     }
-
-    fn set_state(&mut self, _state: String) {
-        log::debug!("Setting state in memory storage: {}", _state)
+    #[instrument(skip_all)]
+    pub fn get_certificate_ids_by_issuer_state(&self, issuer_state: &str) -> Option<Vec<String>> {
+        let issuer_states = self.issuer_states.lock().unwrap();
+        issuer_states.get(issuer_state).cloned()
+    }
+    /// Bundles all certificates of a learner for a Comprehensive Learner Record, the bundle id
+    /// is offered like a certificate id.
+    #[instrument(skip_all)]
+    pub fn create_clr_bundle(&self, profile_id: &str) -> Option<String> {
+        let certificate_ids = self.find_certificate_ids_by_profile(profile_id);
+        if certificate_ids.is_empty() {
+            return None;
+        }
+        let clr_id = Uuid::new_v4().to_string();
+        log::info!(
+            "Bundling certificates {:?} of {} as CLR {}",
+            certificate_ids,
+            profile_id,
+            clr_id
+        );
+        {
+            let mut bundles = self.clr_bundles.lock().unwrap();
+            bundles.insert(
+                clr_id.clone(),
+                ClrBundle {
+                    profile_id: profile_id.to_string(),
+                    certificate_ids,
+                },
+            );
+        }
+        self.persist();
+        Some(clr_id)
+    }
+    #[instrument(skip_all)]
+    pub fn get_clr_bundle(&self, clr_id: &str) -> Option<ClrBundle> {
+        let bundles = self.clr_bundles.lock().unwrap();
+        bundles.get(clr_id).cloned()
+    }
+    /// Credential configuration a granted certificate id is issued as.
+    pub fn credential_configuration_id(&self, certificate_id: &str) -> &'static str {
+        match self.get_clr_bundle(certificate_id) {
+            Some(_) => CLR_CREDENTIAL_CONFIGURATION_ID,
+            None => CERTIFICATE_CREDENTIAL_CONFIGURATION_ID,
+        }
+    }
+    /// Returns the ids of all certificates of a learner that have not been revoked.
+    #[instrument(skip_all)]
+    pub fn find_certificate_ids_by_profile(&self, profile_id: &str) -> Vec<String> {
+        let certificate_ids: Vec<String> = {
+            let certificates = self.certificates.lock().unwrap();
+            certificates
+                .iter()
+                .filter(|(_, certificate)| certificate.profile_id.as_deref() == Some(profile_id))
+                .map(|(certificate_id, _)| certificate_id.clone())
+                .collect()
+        };
+        certificate_ids
+            .into_iter()
+            .filter(|certificate_id| !self.is_revoked(certificate_id))
+            .collect()
     }
-}
-
-#[instrument(skip_all)]
-pub fn get_issuer_did() -> String {
-    let (priv_key, _) = load_config();
-
-    let issuer_key: PatchedKeyPair = generate::<Ed25519KeyPair>(Some(priv_key.as_bytes()));
 
-    let document: Document = issuer_key.get_did_document(did_key::Config::default());
-    document.id
+    #[instrument(skip_all)]
+    pub fn start_authorization_session(
+        &self,
+        request: AuthorizationRequestQuery,
+        code_challenge: String,
+    ) -> AuthorizationSession {
+        let session = AuthorizationSession {
+            id: Uuid::new_v4().to_string(),
+            client_id: request.client_id,
+            redirect_uri: request.redirect_uri,
+            state: request.state,
+            code_challenge,
+            issuer_state: request.issuer_state,
+            created_at: Utc::now(),
+            federated_login: None,
+        };
+        let mut sessions = self.authorization_sessions.lock().unwrap();
+        sessions.insert(session.id.clone(), session.clone());
+        session
+    }
+    /// The authorization session, unless the learner took too long to sign in.
+    #[instrument(skip_all)]
+    pub fn get_authorization_session(&self, session_id: &str) -> Option<AuthorizationSession> {
+        let sessions = self.authorization_sessions.lock().unwrap();
+        sessions
+            .get(session_id)
+            .filter(|session| !session.is_expired())
+            .cloned()
+    }
+    #[instrument(skip_all)]
+    pub fn set_federated_login(&self, session_id: &str, federated_login: FederatedLogin) {
+        let mut sessions = self.authorization_sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.federated_login = Some(federated_login);
+        }
+    }
+    /// Finishes the session and issues a one-time authorization code for the certificates.
+    #[instrument(skip_all)]
+    pub fn issue_authorization_code(
+        &self,
+        session_id: &str,
+        profile_id: &str,
+        certificate_ids: Vec<String>,
+    ) -> Option<String> {
+        let session = {
+            let mut sessions = self.authorization_sessions.lock().unwrap();
+            sessions.remove(session_id)?
+        };
+        if session.is_expired() {
+            log::warn!("Authorization session {} expired", session.id);
+            return None;
+        }
+        let code = generate_authorization_code(32);
+        log::info!(
+            "Issuing authorization code for profile {} with certificate ids: {:?}",
+            profile_id,
+            certificate_ids
+        );
+        let mut codes = self.authorization_codes.lock().unwrap();
+        codes.insert(
+            code.clone(),
+            AuthorizationCodeGrant {
+                client_id: session.client_id,
+                redirect_uri: session.redirect_uri,
+                code_challenge: session.code_challenge,
+                profile_id: profile_id.to_string(),
+                certificate_ids,
+                expires_at: Utc::now() + Duration::minutes(AUTHORIZATION_CODE_TTL_MINUTES),
+            },
+        );
+        Some(code)
+    }
+    #[instrument(skip_all)]
+    pub fn cancel_authorization_session(&self, session_id: &str) -> Option<AuthorizationSession> {
+        let mut sessions = self.authorization_sessions.lock().unwrap();
+        sessions.remove(session_id)
+    }
+    /// Exchanges an authorization code for the certificate ids it was issued for.
+    /// The code can only be used once, by the client it was issued to, with the redirect URI
+    /// of the authorization request and the matching PKCE verifier.
+    #[instrument(skip_all)]
+    pub fn redeem_authorization_code(
+        &self,
+        code: &str,
+        client_id: Option<&str>,
+        code_verifier: Option<&str>,
+        redirect_uri: Option<&str>,
+    ) -> Option<Vec<String>> {
+        let grant = {
+            let mut codes = self.authorization_codes.lock().unwrap();
+            codes.remove(code)?
+        };
+        if grant.expires_at <= Utc::now() {
+            log::warn!("Authorization code expired at {}", grant.expires_at);
+            return None;
+        }
+        if client_id != Some(grant.client_id.as_str()) {
+            log::warn!("Authorization code was issued to another client");
+            return None;
+        }
+        if redirect_uri != Some(grant.redirect_uri.as_str()) {
+            log::warn!("Redirect URI does not match the authorization request");
+            return None;
+        }
+        match code_verifier {
+            Some(code_verifier) if verify_pkce(code_verifier, &grant.code_challenge) => {
+                Some(grant.certificate_ids)
+            }
+            _ => {
+                log::warn!("PKCE verification failed for profile {}", grant.profile_id);
+                None
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub fn store_presentation_session(&self, session: PresentationSession) {
+        let mut sessions = self.presentation_sessions.lock().unwrap();
+        sessions.insert(session.id.clone(), session);
+    }
+
+    #[instrument(skip_all)]
+    pub fn get_presentation_session(&self, session_id: &str) -> Option<PresentationSession> {
+        let sessions = self.presentation_sessions.lock().unwrap();
+        sessions.get(session_id).cloned()
+    }
+
+    /// Records the outcome of a pending presentation session, each session can only be answered once.
+    #[instrument(skip_all)]
+    pub fn complete_presentation_session(
+        &self,
+        session_id: &str,
+        status: PresentationStatus,
+        result: PresentationResult,
+    ) -> Option<PresentationSession> {
+        let mut sessions = self.presentation_sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(session_id)
+            .filter(|session| session.status == PresentationStatus::Pending)?;
+        session.status = status;
+        session.result = Some(result);
+        Some(session.clone())
+    }
+
+    /// Removes offers whose pre-authorized code was not redeemed in time as well as expired access and
+    /// refresh tokens, authorization sessions and codes and presentation sessions. Redeemed offers are
+    /// dropped silently once they expire.
+    #[instrument(skip_all)]
+    pub fn expire_offers(&self) -> usize {
+        let now = Utc::now();
+        let (expired, removed): (Vec<PreAuthorizedOffer>, usize) = {
+            let mut codes = self.pre_authorized_codes.lock().unwrap();
+            let expired_codes: Vec<String> = codes
+                .iter()
+                .filter(|(_, offer)| offer.expires_at <= now)
+                .map(|(code, _)| code.clone())
+                .collect();
+            let removed: Vec<PreAuthorizedOffer> = expired_codes
+                .into_iter()
+                .filter_map(|code| codes.remove(&code))
+                .collect();
+            let count = removed.len();
+            (
+                removed
+                    .into_iter()
+                    .filter(|offer| offer.redeemed_at.is_none())
+                    .collect(),
+                count,
+            )
+        };
+
+        let families: Vec<String> = {
+            let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+            refresh_tokens.retain(|_, grant| grant.expires_at > now);
+            refresh_tokens
+                .values()
+                .map(|grant| grant.family_id.clone())
+                .collect()
+        };
+        self.token_families
+            .lock()
+            .unwrap()
+            .retain(|family_id, _| families.contains(family_id));
+        self.authorization_sessions
+            .lock()
+            .unwrap()
+            .retain(|_, session| !session.is_expired());
+        self.authorization_codes
+            .lock()
+            .unwrap()
+            .retain(|_, grant| grant.expires_at > now);
+        self.access_tokens
+            .lock()
+            .unwrap()
+            .retain(|_, grant| grant.expires_at > now);
+        let expired_claims = {
+            let mut claims = self.access_token_claims.lock().unwrap();
+            let count = claims.len();
+            claims.retain(|_, claim| claim.expires_at > now);
+            count - claims.len()
+        };
+        // Keep the outcome of presentations around for a while, so relying parties can fetch it
+        self.presentation_sessions
+            .lock()
+            .unwrap()
+            .retain(|_, session| session.expires_at + Duration::hours(OFFER_TTL_HOURS) > now);
+
+        if removed > 0 || expired_claims > 0 {
+            self.persist();
+        }
+        for offer in &expired {
+            log::info!(
+                "Offer for certificate ids {:?} expired at {}",
+                offer.certificate_ids,
+                offer.expires_at
+            );
+            self.notify(
+                WebhookEventType::OfferExpired,
+                json!({
+                    "certificate_ids": offer.certificate_ids,
+                    "expired_at": offer.expires_at,
+                }),
+            );
+        }
+        expired.len()
+    }
+
+    #[instrument(skip_all)]
+    pub fn revoke_certificate(&self, certificate_id: &str) -> bool {
+        if self.get_certificate(certificate_id).is_none() {
+            return false;
+        }
+        let revoked_at = Utc::now();
+        let newly_revoked = {
+            let mut revoked = self.revoked_certificates.lock().unwrap();
+            revoked
+                .insert(certificate_id.to_string(), revoked_at)
+                .is_none()
+        };
+        if newly_revoked {
+            log::info!("Revoked certificate with id: {}", certificate_id);
+            self.persist();
+            self.notify(
+                WebhookEventType::CredentialRevoked,
+                json!({
+                    "certificate_id": certificate_id,
+                    "revoked_at": revoked_at,
+                }),
+            );
+        }
+        true
+    }
+
+    #[instrument(skip_all)]
+    pub fn is_revoked(&self, certificate_id: &str) -> bool {
+        let revoked = self.revoked_certificates.lock().unwrap();
+        revoked.contains_key(certificate_id)
+    }
+
+    #[instrument(skip_all)]
+    pub fn get_revocation_date(&self, certificate_id: &str) -> Option<DateTime<Utc>> {
+        let revoked = self.revoked_certificates.lock().unwrap();
+        revoked.get(certificate_id).cloned()
+    }
+
+    /// Makes an issued credential available on its public verification page.
+    fn record_public_credential(&self, credential: &serde_json::Value, certificate_id: &str) {
+        if let Some(credential_id) = public_credential_id(credential) {
+            {
+                let mut credentials = self.public_credentials.lock().unwrap();
+                credentials.insert(
+                    credential_id,
+                    PublicCredential {
+                        certificate_id: certificate_id.to_string(),
+                        issued_at: Utc::now(),
+                    },
+                );
+            }
+            self.persist();
+        }
+    }
+
+    /// An issued credential by the id of its verification page, regardless of the learner's visibility.
+    #[instrument(skip_all)]
+    pub fn get_public_credential(&self, credential_id: &str) -> Option<PublicCredential> {
+        let credentials = self.public_credentials.lock().unwrap();
+        credentials.get(credential_id).cloned()
+    }
+
+    /// What the verification page shows about a credential, unless its learner opted out.
+    #[instrument(skip_all)]
+    pub fn get_public_verification(&self, credential_id: &str) -> Option<PublicVerification> {
+        let credential = self.get_public_credential(credential_id)?;
+        let certificate = self.get_certificate(&credential.certificate_id)?;
+        if let Some(profile_id) = &certificate.profile_id {
+            if !self.is_publicly_visible(profile_id) {
+                return None;
+            }
+        }
+        Some(PublicVerification::new(
+            credential_id,
+            &credential,
+            &certificate,
+            self.get_revocation_date(&credential.certificate_id),
+        ))
+    }
+
+    /// Opts a learner in or out of showing their credentials on the public verification page.
+    #[instrument(skip_all)]
+    pub fn set_public_visibility(&self, profile_id: &str, public: bool) {
+        {
+            let mut opt_outs = self.verification_opt_outs.lock().unwrap();
+            match public {
+                true => opt_outs.remove(profile_id),
+                false => opt_outs.insert(profile_id.to_string(), Utc::now()),
+            };
+        }
+        self.persist();
+        log::info!(
+            "Public verification of the credentials of {} is {}",
+            profile_id,
+            if public { "enabled" } else { "disabled" }
+        );
+    }
+
+    #[instrument(skip_all)]
+    pub fn is_publicly_visible(&self, profile_id: &str) -> bool {
+        let opt_outs = self.verification_opt_outs.lock().unwrap();
+        !opt_outs.contains_key(profile_id)
+    }
+
+    #[instrument(skip_all)]
+    pub fn store_endorsement(&self, endorsement: Endorsement) {
+        log::info!(
+            "Storing endorsement {} of {} by {}",
+            endorsement.id,
+            endorsement.target_id,
+            endorsement.partner_id
+        );
+        {
+            let mut endorsements = self.endorsements.lock().unwrap();
+            endorsements.push(endorsement);
+        }
+        self.persist();
+    }
+
+    #[instrument(skip_all)]
+    pub fn get_endorsements(&self, target_id: Option<&str>) -> Vec<Endorsement> {
+        let endorsements = self.endorsements.lock().unwrap();
+        endorsements
+            .iter()
+            .filter(|endorsement| target_id.map_or(true, |id| endorsement.target_id == id))
+            .cloned()
+            .collect()
+    }
+
+    /// Embeds the stored endorsements into the issuer and achievements of a credential,
+    /// including the credentials nested in a CLR.
+    pub fn endorse_credential(&self, credential: &mut serde_json::Value) {
+        let endorsements = self.get_endorsements(None);
+        if endorsements.is_empty() {
+            return;
+        }
+        embed_endorsements(&mut credential["issuer"], &endorsements);
+        let subject = &mut credential["credentialSubject"];
+        match subject["achievement"].as_array_mut() {
+            Some(achievements) => achievements
+                .iter_mut()
+                .for_each(|achievement| embed_endorsements(achievement, &endorsements)),
+            None => embed_endorsements(&mut subject["achievement"], &endorsements),
+        }
+        if let Some(nested) = subject["verifiableCredential"].as_array_mut() {
+            nested
+                .iter_mut()
+                .for_each(|credential| self.endorse_credential(credential));
+        }
+    }
+
+    /// Builds the verifiable credential for a certificate and signs it as JWT.
+    #[instrument(skip_all)]
+    pub fn sign_certificate(
+        &self,
+        access_token: &str,
+        certificate_id: &str,
+        certificate: CertificateData,
+        subject_did: Url,
+        issuer_did: String,
+        signer: SigningSubject,
+    ) -> Option<CredentialResponseType> {
+        let mut verifiable_credential = certificate.to_verifiable_credential(&subject_did);
+        self.endorse_credential(&mut verifiable_credential);
+        let verifiable_credential = self.validated_credential(
+            CERTIFICATE_CREDENTIAL_CONFIGURATION_ID,
+            certificate_id,
+            verifiable_credential,
+        )?;
+        let response = self.sign_credential(
+            access_token,
+            certificate_id,
+            verifiable_credential.clone(),
+            subject_did,
+            issuer_did,
+            signer,
+        )?;
+        self.record_public_credential(&verifiable_credential, certificate_id);
+        Some(response)
+    }
+
+    /// Builds the Comprehensive Learner Record of a bundle and signs it as JWT.
+    #[instrument(skip_all)]
+    pub fn sign_clr(
+        &self,
+        access_token: &str,
+        clr_id: &str,
+        bundle: ClrBundle,
+        subject_did: Url,
+        issuer_did: String,
+        signer: SigningSubject,
+    ) -> Option<CredentialResponseType> {
+        let (certificate_ids, certificates): (Vec<&String>, Vec<CertificateData>) = bundle
+            .certificate_ids
+            .iter()
+            .filter(|certificate_id| !self.is_revoked(certificate_id))
+            .filter_map(|certificate_id| {
+                self.get_certificate(certificate_id)
+                    .map(|certificate| (certificate_id, certificate))
+            })
+            .unzip();
+        if certificates.is_empty() {
+            log::error!("No certificates left for the CLR of {}", bundle.profile_id);
+            return None;
+        }
+        let mut verifiable_credential = clr_credential(&certificates, &subject_did);
+        self.endorse_credential(&mut verifiable_credential);
+
+        let mut nested = vec![];
+        for (credential, certificate_id) in verifiable_credential["credentialSubject"]
+            ["verifiableCredential"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .zip(&certificate_ids)
+        {
+            nested.push(self.validated_credential(
+                CERTIFICATE_CREDENTIAL_CONFIGURATION_ID,
+                certificate_id,
+                credential,
+            )?);
+        }
+        // The record is validated with its credentials embedded, the way verifiers see it once
+        // they decoded the nested JWTs
+        verifiable_credential["credentialSubject"]["verifiableCredential"] = json!(nested);
+        let mut verifiable_credential = self.validated_credential(
+            CLR_CREDENTIAL_CONFIGURATION_ID,
+            clr_id,
+            verifiable_credential,
+        )?;
+
+        // Each nested achievement credential is signed on its own, so it can be verified
+        // without the CLR around it
+        let mut nested_jwts = vec![];
+        for credential in &nested {
+            nested_jwts.push(self.encode_credential(
+                credential.clone(),
+                &subject_did,
+                &issuer_did,
+                &signer,
+            )?);
+        }
+        verifiable_credential["credentialSubject"]["verifiableCredential"] = json!(nested_jwts);
+
+        let response = self.sign_credential(
+            access_token,
+            clr_id,
+            verifiable_credential,
+            subject_did,
+            issuer_did,
+            signer,
+        )?;
+        // Only published once the whole record has been signed
+        for (credential, certificate_id) in nested.iter().zip(certificate_ids) {
+            self.record_public_credential(credential, certificate_id);
+        }
+        Some(response)
+    }
+
+    /// Adds the `credentialSchema` of the credential configuration to a credential and validates
+    /// the credential against it, invalid credentials are never issued.
+    fn validated_credential(
+        &self,
+        credential_configuration_id: &str,
+        certificate_id: &str,
+        mut verifiable_credential: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        if let Some(credential_schema) = credential_schema(credential_configuration_id) {
+            verifiable_credential["credentialSchema"] = credential_schema;
+        }
+        log::debug!("Verifiable Credential: {:?}", verifiable_credential);
+
+        if let Err(e) = validate_credential(credential_configuration_id, &verifiable_credential) {
+            log::error!("Refusing to issue certificate {}: {}", certificate_id, e);
+            return None;
+        }
+        Some(verifiable_credential)
+    }
+
+    /// Signs a validated credential as JWT.
+    fn encode_credential(
+        &self,
+        verifiable_credential: serde_json::Value,
+        subject_did: &Url,
+        issuer_did: &str,
+        signer: &SigningSubject,
+    ) -> Option<serde_json::Value> {
+        let credential = block_on(async {
+            jwt::encode(
+                signer.clone(),
+                Header::new(Algorithm::EdDSA),
+                VerifiableCredentialJwt::builder()
+                    .sub(subject_did.clone())
+                    .iss(issuer_did.to_string())
+                    .iat(0)
+                    .exp(9999999999i64)
+                    .verifiable_credential(verifiable_credential)
+                    .build()
+                    .ok(),
+                "did:key",
+            )
+            .await
+            .ok()
+        })?;
+        Some(json!(credential))
+    }
+
+    /// Signs a validated credential of a certificate as JWT and tells the webhooks it was issued.
+    fn sign_credential(
+        &self,
+        access_token: &str,
+        certificate_id: &str,
+        verifiable_credential: serde_json::Value,
+        subject_did: Url,
+        issuer_did: String,
+        signer: SigningSubject,
+    ) -> Option<CredentialResponseType> {
+        let credential_id = verifiable_credential["id"].clone();
+        let credential =
+            self.encode_credential(verifiable_credential, &subject_did, &issuer_did, &signer)?;
+
+        self.notify(
+            WebhookEventType::CredentialIssued,
+            json!({
+                "certificate_id": certificate_id,
+                "credential_id": credential_id,
+                "subject_did": subject_did,
+            }),
+        );
+
+        let notification_id = self.issue_notification_id(access_token, certificate_id);
+
+        Some(CredentialResponseType::Immediate {
+            credential,
+            notification_id: Some(notification_id),
+        })
+    }
+
+    /// Issues the credential for a certificate of the access token's grant, deferred if the
+    /// certificate still needs to be reviewed.
+    #[instrument(skip_all)]
+    pub fn issue_certificate_credential(
+        &self,
+        access_token: &str,
+        certificate_id: Option<String>,
+        subject_did: Url,
+        issuer_did: String,
+        signer: SigningSubject,
+    ) -> Option<CredentialResponse> {
+        log::debug!("certificate_id: {:?}", certificate_id);
+
+        if certificate_id
+            .as_deref()
+            .map_or(false, |id| self.is_revoked(id))
+        {
+            log::error!("Certificate {:?} has been revoked", certificate_id);
+            return None;
+        }
+
+        if let Some((clr_id, bundle)) = certificate_id
+            .clone()
+            .and_then(|id| self.get_clr_bundle(&id).map(|bundle| (id, bundle)))
+        {
+            let credential = self.sign_clr(
+                access_token,
+                &clr_id,
+                bundle,
+                subject_did,
+                issuer_did,
+                signer,
+            )?;
+            return Some(CredentialResponse {
+                credential,
+                c_nonce: Some(C_NONCE.clone()),
+                c_nonce_expires_in: Some(86400),
+            });
+        }
+
+        let certificate = certificate_id.clone().and_then(|id| {
+            self.get_certificate(&id)
+                .map(|certificate| (id, certificate))
+        });
+
+        let (certificate_id, certificate) = match certificate {
+            Some(certificate) => certificate,
+            None => {
+                log::error!(
+                    "Certificate not found for certificate id: {:?}",
+                    certificate_id
+                );
+                return None;
+            }
+        };
+
+        if certificate.requires_review && !self.is_review_approved(access_token, &certificate_id) {
+            let transaction_id =
+                self.request_review(access_token, &certificate_id, certificate, &subject_did);
+            return Some(CredentialResponse {
+                credential: CredentialResponseType::Deferred { transaction_id },
+                c_nonce: Some(C_NONCE.clone()),
+                c_nonce_expires_in: Some(86400),
+            });
+        }
+
+        let credential = self.sign_certificate(
+            access_token,
+            &certificate_id,
+            certificate,
+            subject_did,
+            issuer_did,
+            signer,
+        )?;
+
+        Some(CredentialResponse {
+            credential,
+            c_nonce: Some(C_NONCE.clone()),
+            c_nonce_expires_in: Some(86400),
+        })
+    }
+
+    #[instrument(skip_all)]
+    pub fn request_review(
+        &self,
+        access_token: &str,
+        certificate_id: &str,
+        certificate: CertificateData,
+        subject_did: &Url,
+    ) -> String {
+        let transaction_id = Uuid::new_v4().to_string();
+        log::info!(
+            "Certificate {} requires review, deferring with transaction id {}",
+            certificate_id,
+            transaction_id
+        );
+        self.reviews.lock().unwrap().insert(
+            transaction_id.clone(),
+            PendingReview {
+                transaction_id: transaction_id.clone(),
+                certificate_id: certificate_id.to_string(),
+                certificate,
+                subject_did: subject_did.to_string(),
+                status: ReviewStatus::Pending,
+                reason: None,
+                requested_at: Utc::now(),
+                reviewed_at: None,
+                access_token_hash: access_token_hash(access_token),
+            },
+        );
+        self.persist();
+        transaction_id
+    }
+
+    #[instrument(skip_all)]
+    pub fn get_reviews(&self, status: Option<ReviewStatus>) -> Vec<PendingReview> {
+        let reviews = self.reviews.lock().unwrap();
+        let mut reviews: Vec<PendingReview> = reviews
+            .values()
+            .filter(|review| status.map_or(true, |status| review.status == status))
+            .cloned()
+            .collect();
+        reviews.sort_by_key(|review| review.requested_at);
+        reviews
+    }
+
+    /// Approves or rejects a pending review. Returns `None` if the transaction is unknown
+    /// or was already decided.
+    #[instrument(skip_all)]
+    pub fn decide_review(
+        &self,
+        transaction_id: &str,
+        status: ReviewStatus,
+        reason: Option<String>,
+    ) -> Option<PendingReview> {
+        self.synchronized(|| {
+            let mut reviews = self.reviews.lock().unwrap();
+            let decided = reviews
+                .get_mut(transaction_id)
+                .filter(|review| review.status == ReviewStatus::Pending)
+                .map(|review| {
+                    log::info!(
+                        "Review {} for certificate {} is now {:?}",
+                        transaction_id,
+                        review.certificate_id,
+                        status
+                    );
+                    review.status = status;
+                    review.reason = reason;
+                    review.reviewed_at = Some(Utc::now());
+                    review.clone()
+                });
+            let changed = decided.is_some();
+            (decided, changed)
+        })
+    }
+
+    /// Whether a teacher approved the certificate for a request made with this access token.
+    fn is_review_approved(&self, access_token: &str, certificate_id: &str) -> bool {
+        let access_token_hash = access_token_hash(access_token);
+        let reviews = self.reviews.lock().unwrap();
+        reviews.values().any(|review| {
+            review.access_token_hash == access_token_hash
+                && review.certificate_id == certificate_id
+                && review.status == ReviewStatus::Approved
+        })
+    }
+
+    /// Answers a request at the deferred credential endpoint.
+    #[instrument(skip_all)]
+    pub fn get_deferred_credential_response(
+        &self,
+        access_token: &str,
+        transaction_id: &str,
+        signer: SigningSubject,
+    ) -> Result<CredentialResponseType, DeferredCredentialErrorResponse> {
+        let review = {
+            let reviews = self.reviews.lock().unwrap();
+            reviews
+                .get(transaction_id)
+                .filter(|review| review.access_token_hash == access_token_hash(access_token))
+                .cloned()
+                .ok_or_else(DeferredCredentialErrorResponse::invalid_transaction_id)?
+        };
+
+        match review.status {
+            ReviewStatus::Pending => Err(DeferredCredentialErrorResponse::issuance_pending()),
+            ReviewStatus::Rejected => Err(
+                DeferredCredentialErrorResponse::credential_request_denied(review.reason),
+            ),
+            ReviewStatus::Approved => {
+                if self.is_revoked(&review.certificate_id) {
+                    return Err(DeferredCredentialErrorResponse::credential_request_denied(
+                        Some("Certificate has been revoked".to_string()),
+                    ));
+                }
+                let subject_did: Url = review
+                    .subject_did
+                    .parse()
+                    .map_err(|_| DeferredCredentialErrorResponse::invalid_transaction_id())?;
+                let credential = self
+                    .sign_certificate(
+                        access_token,
+                        &review.certificate_id,
+                        review.certificate,
+                        subject_did,
+                        get_issuer_did(),
+                        signer,
+                    )
+                    .ok_or_else(DeferredCredentialErrorResponse::invalid_transaction_id)?;
+
+                // A transaction id can only be redeemed once
+                self.reviews.lock().unwrap().remove(transaction_id);
+                self.persist();
+                Ok(credential)
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub fn issue_notification_id(&self, access_token: &str, certificate_id: &str) -> String {
+        let notification_id = Uuid::new_v4().to_string();
+        let mut notification_ids = self.notification_ids.lock().unwrap();
+        notification_ids.insert(
+            notification_id.clone(),
+            IssuedCredential {
+                certificate_id: certificate_id.to_string(),
+                access_token: access_token.to_string(),
+            },
+        );
+        notification_id
+    }
+
+    /// Records a wallet notification, returning the certificate id it belongs to.
+    /// Returns `None` if the notification id is unknown or was issued for another access token.
+    #[instrument(skip_all)]
+    pub fn record_notification(
+        &self,
+        access_token: &str,
+        request: NotificationRequest,
+    ) -> Option<String> {
+        let certificate_id = {
+            let notification_ids = self.notification_ids.lock().unwrap();
+            notification_ids
+                .get(&request.notification_id)
+                .filter(|issued| issued.access_token == access_token)
+                .map(|issued| issued.certificate_id.clone())?
+        };
+
+        log::info!(
+            "Certificate {} received notification {:?}",
+            certificate_id,
+            request.event
+        );
+        let mut notifications = self.notifications.lock().unwrap();
+        notifications
+            .entry(certificate_id.clone())
+            .or_default()
+            .push(CredentialNotification {
+                notification_id: request.notification_id,
+                event: request.event,
+                event_description: request.event_description,
+                received_at: Utc::now(),
+            });
+        Some(certificate_id)
+    }
+
+    #[instrument(skip_all)]
+    pub fn get_notifications(&self, certificate_id: &str) -> Vec<CredentialNotification> {
+        let notifications = self.notifications.lock().unwrap();
+        notifications
+            .get(certificate_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl<CFC: CredentialFormatCollection + DeserializeOwned> Storage<CFC> for MemoryStorage {
+    #[instrument(skip_all)]
+    fn get_credential_configurations_supported(
+        &self,
+    ) -> HashMap<String, CredentialConfigurationsSupportedObject<CFC>> {
+        log::debug!("get_credential_configurations_supported");
+        let display_config = load_display_config()
+            .map_err(|e| log::error!("Failed to load the display configuration: {}", e))
+            .ok();
+        CREDENTIAL_CONFIGURATION_FILES
+            .into_iter()
+            .map(|(credential_configuration_id, path)| {
+                let mut configuration: serde_json::Value =
+                    serde_json::from_reader(File::open(path).unwrap()).unwrap();
+                if let Some(configuration) = configuration.as_object_mut() {
+                    configuration.remove(CREDENTIAL_SCHEMA_FIELD);
+                }
+                if let Some(display) = display_config
+                    .as_ref()
+                    .and_then(|config| config.credential_display(credential_configuration_id))
+                {
+                    configuration["display"] = json!(display);
+                }
+                (
+                    credential_configuration_id.to_string(),
+                    serde_json::from_value(configuration).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[instrument(skip_all)]
+    fn get_authorization_code(&self) -> Option<AuthorizationCode> {
+        let state = Uuid::new_v4().to_string();
+        log::debug!("get_authorization_code {}", state);
+        Some(AuthorizationCode {
+            issuer_state: Some(state),
+            authorization_server: None,
+        })
+    }
+
+    #[instrument(skip_all)]
+    fn get_authorization_response(&self) -> Option<AuthorizationResponse> {
+        let code = generate_authorization_code(16);
+
+        Some(AuthorizationResponse { code, state: None })
+    }
+
+    #[instrument(skip_all)]
+    fn get_pre_authorized_code(&self) -> Option<PreAuthorizedCode> {
+        Some(generate_pre_authorized_code())
+    }
+
+    #[instrument(skip_all)]
+    fn get_token_response(&self, token_request: TokenRequest) -> Option<TokenResponse> {
+        self.get_bound_token_response(token_request, None, None, false)
+    }
+
+    #[instrument(skip_all)]
+    fn get_credential_response(
+        &self,
+        access_token: String,
+        subject_did: Url,
+        issuer_did: Url,
+        credential_format: CFC,
+        signer: SigningSubject,
+    ) -> Option<CredentialResponse> {
+        log::debug!("Getting credential response for {}", access_token);
+
+        log::debug!("access_token: {}", access_token);
+        log::debug!("credential_format: {:?}", credential_format);
+        log::debug!("subject did: {}", subject_did);
+        log::debug!("issuer did: {}", issuer_did);
+
+        let issuer_did = get_issuer_did();
+        log::debug!("updated issuer did {}", issuer_did);
+
+        let type_ = match serde_json::from_value::<CredentialFormats<WithParameters>>(
+            serde_json::to_value(credential_format).unwrap(),
+        )
+        .unwrap()
+        {
+            CredentialFormats::JwtVcJson(credential) => {
+                credential.parameters.credential_definition.type_
+            }
+            _ => unreachable!("Credential format not supported"),
+        };
+
+        let certificate_id = self.claim_certificate_id(&access_token);
+        self.issue_certificate_credential(
+            &access_token,
+            certificate_id,
+            subject_did,
+            issuer_did,
+            signer,
+        )
+    }
+
+    fn get_state(&self) -> Option<String> {
+        log::debug!("Getting state in memory storage");
+        None
+    }
+
+    fn set_state(&mut self, _state: String) {
+        log::debug!("Setting state in memory storage: {}", _state)
+    }
+}
+
+fn modified_at(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[instrument(skip_all)]
+pub fn get_issuer_did() -> String {
+    let priv_key = load_private_key();
+
+    let issuer_key: PatchedKeyPair = generate::<Ed25519KeyPair>(Some(priv_key.as_bytes()));
+
+    let document: Document = issuer_key.get_did_document(did_key::Config::default());
+    document.id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization_data::{
+        pkce_challenge, CredentialAuthorizationDetails, AUTHORIZATION_SESSION_TTL_MINUTES,
+    };
+
+    fn certificate(profile_id: &str) -> CertificateData {
+        serde_json::from_value(json!({
+            "game_path_name": "Introduction to Rust",
+            "total_challenges": 10,
+            "solved_challenges": 8,
+            "performance_percentage": 80,
+            "profile_name": "Alice Rustacean",
+            "profile_id": profile_id,
+            "date": "2024-09-30T08:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn expire(storage: &MemoryStorage, pre_authorized_code: &str) {
+        let mut codes = storage.pre_authorized_codes.lock().unwrap();
+        codes.get_mut(pre_authorized_code).unwrap().expires_at = Utc::now() - Duration::minutes(1);
+    }
+
+    #[test]
+    fn test_pre_authorized_code_is_redeemed_once() {
+        let storage = MemoryStorage::new();
+        storage.store_certificate("certificate-1".to_string(), certificate("alice"));
+        storage
+            .associate_pre_authorized_code("code".to_string(), vec!["certificate-1".to_string()]);
+
+        assert_eq!(
+            storage.redeem_pre_authorized_code("code"),
+            Some(vec!["certificate-1".to_string()])
+        );
+        assert_eq!(storage.redeem_pre_authorized_code("code"), None);
+        assert_eq!(storage.redeem_pre_authorized_code("unknown"), None);
+    }
+
+    #[test]
+    fn test_expired_pre_authorized_code_is_rejected() {
+        let storage = MemoryStorage::new();
+        storage
+            .associate_pre_authorized_code("code".to_string(), vec!["certificate-1".to_string()]);
+        expire(&storage, "code");
+
+        assert_eq!(storage.redeem_pre_authorized_code("code"), None);
+    }
+
+    #[test]
+    fn test_expire_offers_skips_redeemed_offers() {
+        let storage = MemoryStorage::new();
+        storage.associate_pre_authorized_code(
+            "redeemed".to_string(),
+            vec!["certificate-1".to_string()],
+        );
+        storage
+            .associate_pre_authorized_code("unused".to_string(), vec!["certificate-2".to_string()]);
+        storage.associate_pre_authorized_code(
+            "pending".to_string(),
+            vec!["certificate-3".to_string()],
+        );
+        storage.redeem_pre_authorized_code("redeemed").unwrap();
+        expire(&storage, "redeemed");
+        expire(&storage, "unused");
+
+        assert_eq!(storage.expire_offers(), 1);
+        let codes = storage.pre_authorized_codes.lock().unwrap();
+        assert_eq!(codes.keys().collect::<Vec<_>>(), vec!["pending"]);
+    }
+
+    #[test]
+    fn test_notification_is_recorded_for_its_access_token() {
+        let storage = MemoryStorage::new();
+        let notification_id = storage.issue_notification_id("token-1", "certificate-1");
+        let request = |notification_id: &str| {
+            serde_json::from_value::<NotificationRequest>(json!({
+                "notification_id": notification_id,
+                "event": "credential_accepted",
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            storage.record_notification("token-2", request(&notification_id)),
+            None
+        );
+        assert_eq!(
+            storage.record_notification("token-1", request("unknown")),
+            None
+        );
+        assert!(storage.get_notifications("certificate-1").is_empty());
+
+        assert_eq!(
+            storage.record_notification("token-1", request(&notification_id)),
+            Some("certificate-1".to_string())
+        );
+        let notifications = storage.get_notifications("certificate-1");
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].notification_id, notification_id);
+    }
+
+    fn signer() -> SigningSubject {
+        let issuer_key = generate::<Ed25519KeyPair>(Some(b"this-is-a-test-issuer-key"));
+        std::sync::Arc::new(
+            oid4vc_manager::methods::key_method::KeySubject::from_keypair(issuer_key, None),
+        )
+    }
+
+    #[test]
+    fn test_deferred_credential_waits_for_review() {
+        let storage = MemoryStorage::new();
+        storage.store_certificate("certificate-1".to_string(), certificate("alice"));
+        let subject_did: Url = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+            .parse()
+            .unwrap();
+        let transaction_id = storage.request_review(
+            "token-1",
+            "certificate-1",
+            certificate("alice"),
+            &subject_did,
+        );
+
+        let error = |access_token: &str| {
+            storage
+                .get_deferred_credential_response(access_token, &transaction_id, signer())
+                .unwrap_err()
+                .error
+        };
+        assert_eq!(error("token-1"), "issuance_pending");
+        assert_eq!(error("token-2"), "invalid_transaction_id");
+
+        let review = storage
+            .decide_review(
+                &transaction_id,
+                ReviewStatus::Rejected,
+                Some("Incomplete".to_string()),
+            )
+            .unwrap();
+        assert_eq!(review.status, ReviewStatus::Rejected);
+        assert!(storage
+            .decide_review(&transaction_id, ReviewStatus::Approved, None)
+            .is_none());
+
+        let rejection = storage
+            .get_deferred_credential_response("token-1", &transaction_id, signer())
+            .unwrap_err();
+        assert_eq!(rejection.error, "credential_request_denied");
+        assert_eq!(rejection.error_description.as_deref(), Some("Incomplete"));
+    }
+
+    #[test]
+    fn test_deferred_credential_of_revoked_certificate_is_denied() {
+        let storage = MemoryStorage::new();
+        storage.store_certificate("certificate-1".to_string(), certificate("alice"));
+        let subject_did: Url = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+            .parse()
+            .unwrap();
+        let transaction_id = storage.request_review(
+            "token-1",
+            "certificate-1",
+            certificate("alice"),
+            &subject_did,
+        );
+        storage.decide_review(&transaction_id, ReviewStatus::Approved, None);
+        // The approval only holds for the access token the credential was requested with
+        assert!(storage.is_review_approved("token-1", "certificate-1"));
+        assert!(!storage.is_review_approved("token-2", "certificate-1"));
+        storage.revoke_certificate("certificate-1");
+
+        assert_eq!(
+            storage
+                .get_deferred_credential_response("token-1", &transaction_id, signer())
+                .unwrap_err()
+                .error,
+            "credential_request_denied"
+        );
+    }
+
+    fn jwt_payload(jwt: &serde_json::Value) -> serde_json::Value {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        let payload = jwt.as_str().unwrap().split('.').nth(1).unwrap();
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_clr_nests_signed_achievement_credentials() {
+        crate::config::set_test_env();
+        let storage = MemoryStorage::new();
+        storage.store_certificate("certificate-1".to_string(), certificate("alice"));
+        storage.store_certificate("certificate-2".to_string(), certificate("alice"));
+        let clr_id = storage.create_clr_bundle("alice").unwrap();
+        let subject_did: Url = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+            .parse()
+            .unwrap();
+
+        let Some(CredentialResponseType::Immediate { credential, .. }) = storage.sign_clr(
+            "token-1",
+            &clr_id,
+            storage.get_clr_bundle(&clr_id).unwrap(),
+            subject_did,
+            get_issuer_did(),
+            signer(),
+        ) else {
+            panic!("The CLR was not issued");
+        };
+
+        let clr = jwt_payload(&credential)["vc"].clone();
+        let nested = clr["credentialSubject"]["verifiableCredential"]
+            .as_array()
+            .unwrap();
+        assert_eq!(nested.len(), 2);
+        for credential in nested {
+            let claims = jwt_payload(credential);
+            assert_eq!(claims["iss"], json!(get_issuer_did()));
+            assert!(claims["vc"]["type"]
+                .as_array()
+                .unwrap()
+                .contains(&json!("OpenBadgeCredential")));
+            let credential_id = public_credential_id(&claims["vc"]).unwrap();
+            assert!(storage.get_public_verification(&credential_id).is_some());
+        }
+    }
+
+    #[test]
+    fn test_credential_identifiers_of_access_token() {
+        let storage = MemoryStorage::new();
+        storage.store_certificate("certificate-1".to_string(), certificate("alice"));
+        let clr_id = storage.create_clr_bundle("alice").unwrap();
+        storage.associate_access_token(
+            "token-1".to_string(),
+            vec!["certificate-1".to_string(), clr_id.clone()],
+            None,
+        );
+
+        let granted = storage
+            .get_certificate_ids_by_access_token("token-1")
+            .unwrap();
+        let authorization_details =
+            CredentialAuthorizationDetails::for_certificates(granted, |certificate_id| {
+                storage.credential_configuration_id(certificate_id)
+            });
+        assert_eq!(authorization_details.len(), 2);
+        assert_eq!(
+            authorization_details[0].credential_configuration_id,
+            CERTIFICATE_CREDENTIAL_CONFIGURATION_ID
+        );
+        assert_eq!(
+            authorization_details[1].credential_configuration_id,
+            CLR_CREDENTIAL_CONFIGURATION_ID
+        );
+        assert_eq!(
+            authorization_details[1].credential_identifiers,
+            vec![clr_id]
+        );
+        assert!(storage
+            .get_certificate_ids_by_access_token("token-2")
+            .is_none());
+    }
+
+    #[test]
+    fn test_claim_certificate_ids() {
+        let storage = MemoryStorage::new();
+        let certificate_ids: Vec<String> = vec!["certificate-1", "certificate-2", "certificate-3"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        storage.associate_access_token("token-1".to_string(), certificate_ids.clone(), None);
+
+        assert_eq!(
+            storage.claim_certificate_id("token-1"),
+            Some("certificate-1".to_string())
+        );
+        // A batch larger than what is left is refused without claiming anything
+        assert_eq!(storage.claim_certificate_ids("token-1", 3), None);
+        assert_eq!(
+            storage.claim_certificate_ids("token-1", 2),
+            Some(certificate_ids[1..].to_vec())
+        );
+        assert_eq!(storage.claim_certificate_id("token-1"), None);
+        assert_eq!(storage.claim_certificate_id("token-2"), None);
+
+        // Credential identifiers are claimed once, and skipped when claiming in order
+        storage.associate_access_token("token-3".to_string(), certificate_ids.clone(), None);
+        assert!(storage.claim_certificate_identifier("token-3", "certificate-2"));
+        assert!(!storage.claim_certificate_identifier("token-3", "certificate-2"));
+        assert!(!storage.claim_certificate_identifier("token-3", "certificate-4"));
+        assert_eq!(
+            storage.claim_certificate_ids("token-3", 2),
+            Some(vec![
+                "certificate-1".to_string(),
+                "certificate-3".to_string()
+            ])
+        );
+    }
+
+    fn authorization_code(storage: &MemoryStorage, code_verifier: &str) -> String {
+        let request = serde_json::from_value(json!({
+            "response_type": "code",
+            "client_id": "wallet",
+            "redirect_uri": "https://wallet.example.com/callback",
+        }))
+        .unwrap();
+        let session = storage.start_authorization_session(request, pkce_challenge(code_verifier));
+        storage
+            .issue_authorization_code(&session.id, "alice", vec!["certificate-1".to_string()])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_authorization_code_requires_client_and_redirect_uri() {
+        let storage = MemoryStorage::new();
+        let code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let redirect_uri = Some("https://wallet.example.com/callback");
+
+        let code = authorization_code(&storage, code_verifier);
+        let redeem = |code: &str, client_id, redirect_uri| {
+            storage.redeem_authorization_code(code, client_id, Some(code_verifier), redirect_uri)
+        };
+        assert_eq!(redeem(&code, Some("attacker"), redirect_uri), None);
+        // A failed attempt consumes the code
+        assert_eq!(redeem(&code, Some("wallet"), redirect_uri), None);
+
+        let code = authorization_code(&storage, code_verifier);
+        assert_eq!(redeem(&code, Some("wallet"), None), None);
+        let code = authorization_code(&storage, code_verifier);
+        assert_eq!(
+            redeem(
+                &code,
+                Some("wallet"),
+                Some("https://attacker.example.com/callback")
+            ),
+            None
+        );
+        let code = authorization_code(&storage, code_verifier);
+        assert_eq!(
+            storage.redeem_authorization_code(&code, Some("wallet"), Some("wrong"), redirect_uri),
+            None
+        );
+
+        let code = authorization_code(&storage, code_verifier);
+        assert_eq!(
+            redeem(&code, Some("wallet"), redirect_uri),
+            Some(vec!["certificate-1".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_authorization_sessions_and_codes_expire() {
+        let storage = MemoryStorage::new();
+        let code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let redirect_uri = Some("https://wallet.example.com/callback");
+
+        let code = authorization_code(&storage, code_verifier);
+        storage
+            .authorization_codes
+            .lock()
+            .unwrap()
+            .get_mut(&code)
+            .unwrap()
+            .expires_at = Utc::now() - Duration::minutes(1);
+        assert_eq!(
+            storage.redeem_authorization_code(
+                &code,
+                Some("wallet"),
+                Some(code_verifier),
+                redirect_uri
+            ),
+            None
+        );
+
+        let request = serde_json::from_value(json!({
+            "response_type": "code",
+            "client_id": "wallet",
+            "redirect_uri": "https://wallet.example.com/callback",
+        }))
+        .unwrap();
+        let session = storage.start_authorization_session(request, pkce_challenge(code_verifier));
+        storage
+            .authorization_sessions
+            .lock()
+            .unwrap()
+            .get_mut(&session.id)
+            .unwrap()
+            .created_at = Utc::now() - Duration::minutes(AUTHORIZATION_SESSION_TTL_MINUTES);
+        assert!(storage.get_authorization_session(&session.id).is_none());
+
+        let code = authorization_code(&storage, code_verifier);
+        storage
+            .authorization_codes
+            .lock()
+            .unwrap()
+            .get_mut(&code)
+            .unwrap()
+            .expires_at = Utc::now() - Duration::minutes(1);
+        storage.expire_offers();
+        assert!(storage.authorization_sessions.lock().unwrap().is_empty());
+        assert!(storage.authorization_codes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_introspect_jwt_access_token() {
+        let storage = MemoryStorage::new().with_config(Config::new(
+            "this-is-a-test-issuer-key".to_string(),
+            "http://localhost:3000".to_string(),
+        ));
+        let claims = || {
+            AccessTokenClaims::new(
+                "http://localhost:3000",
+                vec!["certificate-1".to_string()],
+                &C_NONCE,
+                None,
+            )
+        };
+
+        let access_token = encode_access_token("this-is-a-test-issuer-key", &claims()).unwrap();
+        let introspection = storage.introspect_access_token(&access_token);
+        assert!(introspection.active);
+        assert_eq!(introspection.certificate_ids, vec!["certificate-1"]);
+
+        let mut expired = claims();
+        expired.exp = Utc::now().timestamp() - 120;
+        let access_token = encode_access_token("this-is-a-test-issuer-key", &expired).unwrap();
+        assert!(!storage.introspect_access_token(&access_token).active);
+
+        let access_token = encode_access_token("another-issuer-key", &claims()).unwrap();
+        assert!(!storage.introspect_access_token(&access_token).active);
+        assert!(!storage.introspect_access_token("unknown").active);
+    }
+
+    fn refresh_token(storage: &MemoryStorage, dpop_jkt: Option<String>) -> String {
+        storage
+            .associate_pre_authorized_code("code".to_string(), vec!["certificate-1".to_string()]);
+        let token_request = serde_json::from_value(json!({
+            "grant_type": "urn:ietf:params:oauth:grant-type:pre-authorized_code",
+            "pre-authorized_code": "code",
+        }))
+        .unwrap();
+        storage
+            .get_bound_token_response(token_request, None, dpop_jkt, true)
+            .unwrap()
+            .refresh_token
+            .unwrap()
+    }
+
+    #[test]
+    fn test_refresh_token_rotation_and_reuse_detection() {
+        let storage = MemoryStorage::new();
+        let first = refresh_token(&storage, None);
+
+        let response = storage.refresh_token_response(&first, None).unwrap();
+        let second = response.refresh_token.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            storage.get_certificate_ids_by_access_token(&response.access_token),
+            Some(vec!["certificate-1".to_string()])
+        );
+
+        // The rotated token is rejected, and its reuse revokes the whole family
+        assert!(storage.refresh_token_response(&first, None).is_none());
+        assert!(storage.refresh_token_response(&second, None).is_none());
+        assert_eq!(
+            storage.get_certificate_ids_by_access_token(&response.access_token),
+            None
+        );
+    }
+
+    #[test]
+    fn test_jwt_access_token_of_reused_family_is_rejected() {
+        let storage = MemoryStorage::new().with_config(Config::new(
+            "this-is-a-test-issuer-key".to_string(),
+            "http://localhost:3000".to_string(),
+        ));
+        let first = refresh_token(&storage, None);
+        storage.refresh_token_response(&first, None).unwrap();
+
+        let claims = AccessTokenClaims::new(
+            "http://localhost:3000",
+            vec!["certificate-1".to_string()],
+            "nonce",
+            None,
+        );
+        let access_token = encode_access_token("this-is-a-test-issuer-key", &claims).unwrap();
+        let family_id = storage.refresh_tokens.lock().unwrap()[&first]
+            .family_id
+            .clone();
+        storage
+            .token_families
+            .lock()
+            .unwrap()
+            .get_mut(&family_id)
+            .unwrap()
+            .push(FamilyAccessToken::Jwt {
+                jti: claims.jti.clone(),
+                expires_at: DateTime::from_timestamp(claims.exp, 0).unwrap(),
+            });
+        assert!(storage.introspect_access_token(&access_token).active);
+
+        assert!(storage.refresh_token_response(&first, None).is_none());
+        assert!(!storage.introspect_access_token(&access_token).active);
+        assert_eq!(storage.claim_certificate_id(&access_token), None);
+    }
+
+    #[test]
+    fn test_refresh_token_of_bound_grant_requires_its_key() {
+        let storage = MemoryStorage::new();
+        let refresh_token = refresh_token(&storage, Some("jkt-1".to_string()));
+
+        assert!(storage
+            .refresh_token_response(&refresh_token, None)
+            .is_none());
+        assert!(storage
+            .refresh_token_response(&refresh_token, Some("jkt-2".to_string()))
+            .is_none());
+
+        let response = storage
+            .refresh_token_response(&refresh_token, Some("jkt-1".to_string()))
+            .unwrap();
+        assert_eq!(response.token_type, DPOP_TOKEN_TYPE);
+        assert_eq!(
+            storage.get_access_token_jkt(&response.access_token),
+            Some("jkt-1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_refresh_token_of_unbound_grant_is_bound_to_presented_key() {
+        let storage = MemoryStorage::new();
+        let refresh_token = refresh_token(&storage, None);
+
+        let response = storage
+            .refresh_token_response(&refresh_token, Some("jkt-1".to_string()))
+            .unwrap();
+        assert_eq!(response.token_type, DPOP_TOKEN_TYPE);
+        assert_eq!(
+            storage.get_access_token_jkt(&response.access_token),
+            Some("jkt-1".to_string())
+        );
+        // The successor refresh token is bound as well
+        let successor = response.refresh_token.unwrap();
+        assert!(storage.refresh_token_response(&successor, None).is_none());
+    }
 }
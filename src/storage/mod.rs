@@ -1,3 +1,5 @@
 mod memory_storage;
+mod snapshot;
 
-pub use memory_storage::MemoryStorage;
+pub use memory_storage::{get_issuer_did, MemoryStorage};
+pub use snapshot::StorageSnapshot;
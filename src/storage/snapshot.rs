@@ -0,0 +1,297 @@
+use super::memory_storage::{AccessTokenClaim, PreAuthorizedOffer};
+use crate::certificate_data::CertificateData;
+use crate::clr_data::ClrBundle;
+use crate::deferred_data::PendingReview;
+use crate::endorsement_data::Endorsement;
+use crate::public_verification::PublicCredential;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+/// How long to wait for another process to finish saving the storage file.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Age after which a lock file is considered left behind by a crashed process.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// The part of the storage that outlives a restart, saved as JSON to the `STORAGE_FILE`.
+/// Tokens and sessions are short-lived and not part of it, only the certificates claimed with
+/// JWT access tokens are, as any instance may be presented such a token. Reviews are kept, so a
+/// teacher's decision survives a restart.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct StorageSnapshot {
+    #[serde(default)]
+    pub(super) certificates: HashMap<String, CertificateData>,
+    #[serde(default)]
+    pub(super) pre_authorized_codes: HashMap<String, PreAuthorizedOffer>,
+    #[serde(default)]
+    pub(super) issuer_states: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub(super) revoked_certificates: HashMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    pub(super) clr_bundles: HashMap<String, ClrBundle>,
+    #[serde(default)]
+    pub(super) endorsements: Vec<Endorsement>,
+    #[serde(default)]
+    pub(super) public_credentials: HashMap<String, PublicCredential>,
+    #[serde(default)]
+    pub(super) verification_opt_outs: HashMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    pub(super) access_token_claims: HashMap<String, AccessTokenClaim>,
+    #[serde(default)]
+    pub(super) reviews: HashMap<String, PendingReview>,
+}
+
+impl StorageSnapshot {
+    /// Loads the snapshot, a missing file is an empty storage.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Saves the snapshot to a temporary file first, so readers never see a partial one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let temporary_path = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+        let result = serde_json::to_writer_pretty(File::create(&temporary_path)?, self)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(std::fs::rename(&temporary_path, path)?));
+        if result.is_err() {
+            std::fs::remove_file(&temporary_path).ok();
+        }
+        result
+    }
+}
+
+/// Entries are compared by their JSON, the stored types have no `PartialEq`.
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Applies the changes `ours` made since `base` to `theirs`, the current content of the file.
+/// Entries either side added, changed or removed are taken over, on conflicting changes `ours`
+/// wins and a removal never undoes a change of the other side.
+pub(super) fn merge_entries<T: Serialize + Clone>(
+    base: &HashMap<String, T>,
+    ours: &HashMap<String, T>,
+    theirs: HashMap<String, T>,
+) -> HashMap<String, T> {
+    let mut merged = theirs;
+    for (key, value) in ours {
+        if !base.get(key).map_or(false, |known| same(known, value)) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    for (key, known) in base {
+        let unchanged = merged.get(key).map_or(false, |value| same(value, known));
+        if !ours.contains_key(key) && unchanged {
+            merged.remove(key);
+        }
+    }
+    merged
+}
+
+/// Three-way merge of the endorsements by their id, in the order they were created.
+pub(super) fn merge_endorsements(
+    base: &[Endorsement],
+    ours: &[Endorsement],
+    theirs: Vec<Endorsement>,
+) -> Vec<Endorsement> {
+    let by_id = |endorsements: Vec<Endorsement>| -> HashMap<String, Endorsement> {
+        endorsements
+            .into_iter()
+            .map(|endorsement| (endorsement.id.clone(), endorsement))
+            .collect()
+    };
+    let mut merged: Vec<Endorsement> =
+        merge_entries(&by_id(base.to_vec()), &by_id(ours.to_vec()), by_id(theirs))
+            .into_values()
+            .collect();
+    merged.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    merged
+}
+
+/// Exclusive lock of a storage file between processes, held while it is read, merged and
+/// saved. The lock file is removed when the lock is dropped.
+pub(super) struct StorageLock {
+    path: PathBuf,
+}
+
+impl StorageLock {
+    pub(super) fn acquire(storage_file: &Path) -> Result<Self> {
+        let path = storage_file.with_extension("lock");
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .map_or(false, |age| age > STALE_LOCK_AGE);
+                    if stale {
+                        log::warn!("Removing stale lock {}", path.display());
+                        std::fs::remove_file(&path).ok();
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                        return Err(anyhow!("Timed out waiting for {}", path.display()));
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deferred_data::ReviewStatus;
+    use crate::storage::MemoryStorage;
+    use serde_json::json;
+
+    #[test]
+    fn test_storage_file() {
+        let storage_file = std::env::temp_dir().join(format!("{}.json", uuid::Uuid::new_v4()));
+        let certificate = serde_json::from_value(json!({
+            "game_path_name": "Introduction to Rust",
+            "total_challenges": 10,
+            "solved_challenges": 8,
+            "performance_percentage": 80,
+            "profile_name": "Alice Rustacean",
+            "date": "2024-09-30T08:00:00Z",
+        }))
+        .unwrap();
+
+        let storage = MemoryStorage::new()
+            .with_storage_file(storage_file.clone())
+            .unwrap();
+        storage.store_certificate("certificate-1".to_string(), certificate);
+        assert!(storage.revoke_certificate("certificate-1"));
+
+        let reloaded = MemoryStorage::new()
+            .with_storage_file(storage_file.clone())
+            .unwrap();
+        assert!(reloaded.get_certificate("certificate-1").is_some());
+        assert!(reloaded.is_revoked("certificate-1"));
+
+        let subject_did = "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+            .parse()
+            .unwrap();
+        let certificate = reloaded.get_certificate("certificate-1").unwrap();
+        let transaction_id =
+            storage.request_review("token-1", "certificate-1", certificate, &subject_did);
+        let reloaded = MemoryStorage::new()
+            .with_storage_file(storage_file.clone())
+            .unwrap();
+        assert!(reloaded
+            .decide_review(&transaction_id, ReviewStatus::Approved, None)
+            .is_some());
+        let reloaded = MemoryStorage::new()
+            .with_storage_file(storage_file.clone())
+            .unwrap();
+        assert_eq!(
+            reloaded.get_reviews(Some(ReviewStatus::Approved))[0].transaction_id,
+            transaction_id
+        );
+        std::fs::remove_file(storage_file).unwrap();
+    }
+
+    #[test]
+    fn test_storages_sharing_a_file() {
+        let storage_file = std::env::temp_dir().join(format!("{}.json", uuid::Uuid::new_v4()));
+        let storage = || {
+            MemoryStorage::new()
+                .with_storage_file(storage_file.clone())
+                .unwrap()
+        };
+        let certificate = |profile_id: &str| {
+            serde_json::from_value(json!({
+                "game_path_name": "Introduction to Rust",
+                "total_challenges": 10,
+                "solved_challenges": 8,
+                "performance_percentage": 80,
+                "profile_name": "Alice Rustacean",
+                "profile_id": profile_id,
+                "date": "2024-09-30T08:00:00Z",
+            }))
+            .unwrap()
+        };
+
+        // Neither save drops what the other one added
+        let server = storage();
+        let command_line = storage();
+        server.store_certificate("certificate-1".to_string(), certificate("alice"));
+        command_line.store_certificate("certificate-2".to_string(), certificate("bob"));
+        let reloaded = storage();
+        assert!(reloaded.get_certificate("certificate-1").is_some());
+        assert!(reloaded.get_certificate("certificate-2").is_some());
+
+        // A removal by one is not undone by the next save of the other
+        server.set_public_visibility("alice", false);
+        let command_line = storage();
+        assert!(!command_line.is_publicly_visible("alice"));
+        command_line.set_public_visibility("alice", true);
+        server.store_certificate("certificate-3".to_string(), certificate("alice"));
+        assert!(server.is_publicly_visible("alice"));
+        let reloaded = storage();
+        assert!(reloaded.is_publicly_visible("alice"));
+        assert!(reloaded.get_certificate("certificate-3").is_some());
+
+        assert!(!storage_file.with_extension("lock").exists());
+        std::fs::remove_file(storage_file).unwrap();
+    }
+
+    #[test]
+    fn test_jwt_access_token_claims_are_shared() {
+        use crate::access_token::{encode_access_token, AccessTokenClaims};
+        use crate::config::Config;
+
+        let storage_file = std::env::temp_dir().join(format!("{}.json", uuid::Uuid::new_v4()));
+        let instance = || {
+            MemoryStorage::new()
+                .with_storage_file(storage_file.clone())
+                .unwrap()
+                .with_config(Config::new(
+                    "this-is-a-test-issuer-key".to_string(),
+                    "http://localhost:3000".to_string(),
+                ))
+        };
+        let claims = AccessTokenClaims::new(
+            "http://localhost:3000",
+            vec!["certificate-1".to_string(), "certificate-2".to_string()],
+            "nonce",
+            None,
+        );
+        let access_token = encode_access_token("this-is-a-test-issuer-key", &claims).unwrap();
+
+        // Each certificate is issued once, whichever instance the wallet asks
+        let first = instance();
+        let second = instance();
+        assert_eq!(
+            first.claim_certificate_id(&access_token),
+            Some("certificate-1".to_string())
+        );
+        assert_eq!(
+            second.claim_certificate_id(&access_token),
+            Some("certificate-2".to_string())
+        );
+        assert_eq!(first.claim_certificate_id(&access_token), None);
+        assert!(!second.claim_certificate_identifier(&access_token, "certificate-1"));
+        std::fs::remove_file(storage_file).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+/// Escapes the characters that have a special meaning in HTML.
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders a template, replacing `{{key}}` with the HTML escaped value and
+/// `{{{key}}}` with the raw value. Raw values must already be safe HTML.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = template.to_string();
+    for (key, value) in values {
+        rendered = rendered.replace(&format!("{{{{{{{}}}}}}}", key), value);
+        rendered = rendered.replace(&format!("{{{{{}}}}}", key), &escape_html(value));
+    }
+    rendered
+}
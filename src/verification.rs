@@ -0,0 +1,787 @@
+use crate::certificate_data::CERTIFICATE_CREDENTIAL_CONFIGURATION_ID;
+use crate::clr_data::CLR_CREDENTIAL_CONFIGURATION_ID;
+use crate::credential_schema::validate_credential;
+use crate::issuer_key::did_key_decoding_key;
+use crate::public_verification::public_credential_id;
+use crate::storage::{get_issuer_did, MemoryStorage};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use url::Url;
+
+/// Multicodec prefix of an Ed25519 public key in `publicKeyMultibase`.
+const ED25519_MULTICODEC_PREFIX: [u8; 2] = [0xed, 0x01];
+
+/// Timeout of fetching a DID document or status list.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest DID document or status list the verifier downloads.
+const MAX_FETCH_BYTES: usize = 1024 * 1024;
+
+/// Credential to verify, only JWT VCs (`jwt_vc_json`) are supported for now.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VerificationRequest {
+    #[serde(default = "default_format")]
+    pub format: String,
+    pub credential: String,
+}
+
+fn default_format() -> String {
+    "jwt_vc_json".to_string()
+}
+
+impl VerificationRequest {
+    pub fn jwt_vc(credential: &str) -> Self {
+        Self {
+            format: default_format(),
+            credential: credential.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of a single check, e.g. of the signature or the revocation status.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VerificationCheck {
+    pub check: String,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl VerificationCheck {
+    fn new(check: &str, status: CheckStatus, message: Option<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            status,
+            message,
+        }
+    }
+}
+
+/// Structured result of verifying a credential, `valid` if no check failed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VerificationReport {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub credential_types: Vec<String>,
+    pub checks: Vec<VerificationCheck>,
+    /// The verified credential, without the JWT envelope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<Value>,
+}
+
+impl VerificationReport {
+    fn push(&mut self, check: &str, result: Result<Option<String>>) {
+        self.checks.push(match result {
+            Ok(message) => VerificationCheck::new(check, CheckStatus::Passed, message),
+            Err(e) => VerificationCheck::new(check, CheckStatus::Failed, Some(e.to_string())),
+        });
+    }
+
+    fn skip(&mut self, check: &str, message: &str) {
+        self.checks.push(VerificationCheck::new(
+            check,
+            CheckStatus::Skipped,
+            Some(message.to_string()),
+        ));
+    }
+
+    fn failed(check: &str, e: anyhow::Error) -> Self {
+        Self {
+            checks: vec![VerificationCheck::new(
+                check,
+                CheckStatus::Failed,
+                Some(e.to_string()),
+            )],
+            ..Default::default()
+        }
+    }
+}
+
+/// URL of the DID document of a `did:web`, e.g. `did:web:example.com:user` to
+/// `https://example.com/user/did.json`.
+pub fn did_web_url(did: &str) -> Result<String> {
+    let identifier = did
+        .strip_prefix("did:web:")
+        .ok_or_else(|| anyhow!("{} is not a did:web", did))?;
+    let mut segments = identifier.split(':');
+    let domain = segments
+        .next()
+        .filter(|domain| !domain.is_empty())
+        .ok_or_else(|| anyhow!("{} has no domain", did))?
+        .replace("%3A", ":");
+    let path: Vec<&str> = segments.collect();
+    Ok(match path.is_empty() {
+        true => format!("https://{}/.well-known/did.json", domain),
+        false => format!("https://{}/{}/did.json", domain, path.join("/")),
+    })
+}
+
+/// Decoding key of a verification method of a DID document.
+fn verification_method_key(method: &Value) -> Result<DecodingKey> {
+    if let Some(jwk) = method.get("publicKeyJwk") {
+        let jwk: Jwk = serde_json::from_value(jwk.clone())?;
+        return Ok(DecodingKey::from_jwk(&jwk)?);
+    }
+    if let Some(key) = method["publicKeyBase58"].as_str() {
+        return Ok(DecodingKey::from_ed_der(&bs58::decode(key).into_vec()?));
+    }
+    if let Some(key) = method["publicKeyMultibase"].as_str() {
+        let encoded = key
+            .strip_prefix('z')
+            .ok_or_else(|| anyhow!("Only base58btc multibase keys are supported"))?;
+        let bytes = bs58::decode(encoded).into_vec()?;
+        let key = bytes
+            .strip_prefix(ED25519_MULTICODEC_PREFIX.as_slice())
+            .unwrap_or(&bytes);
+        return Ok(DecodingKey::from_ed_der(key));
+    }
+    Err(anyhow!("Unsupported verification method"))
+}
+
+/// Decodes a gzip compressed, base64url encoded status list and reads the bit at `index`.
+pub fn status_list_bit(encoded_list: &str, index: usize) -> Result<bool> {
+    // Bitstring status lists are multibase encoded with the base64url prefix `u`
+    let encoded = encoded_list.strip_prefix('u').unwrap_or(encoded_list);
+    let compressed = URL_SAFE_NO_PAD.decode(encoded.trim_end_matches('='))?;
+    let mut bits = vec![];
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut bits)?;
+    let byte = bits
+        .get(index / 8)
+        .ok_or_else(|| anyhow!("Status list index {} is out of range", index))?;
+    Ok(byte & (0x80 >> (index % 8)) != 0)
+}
+
+/// Whether an address is reachable on the internet, the verifier never fetches from loopback,
+/// private or link-local networks on behalf of its callers.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space (RFC 6598) and benchmarking (RFC 2544)
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 224)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10)
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+fn jwt_payload(jwt: &str) -> Result<Value> {
+    let payload = jwt.split('.').nth(1).ok_or_else(|| anyhow!("Not a JWT"))?;
+    Ok(serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?)
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    value
+        .as_str()
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Verifies credentials of any issuer, resolving their DIDs and status lists over HTTP.
+pub struct CredentialVerifier {
+    /// Only resolve what needs no network access, like `did:key`, e.g. for the command line.
+    offline: bool,
+    /// Storage of this issuer, to check the revocation of its own credentials.
+    storage: Option<MemoryStorage>,
+}
+
+impl Default for CredentialVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CredentialVerifier {
+    pub fn new() -> Self {
+        Self {
+            offline: false,
+            storage: None,
+        }
+    }
+
+    /// A verifier that never accesses the network, revocation is not checked.
+    pub fn offline() -> Self {
+        Self {
+            offline: true,
+            ..Self::new()
+        }
+    }
+
+    /// Checks credentials of this issuer against the revocations in its storage, they carry
+    /// no `credentialStatus`.
+    pub fn with_storage(mut self, storage: MemoryStorage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Fetches a document over HTTPS from a public address, with a timeout and size limit.
+    async fn fetch(&self, url: &str) -> Result<String> {
+        let url = Url::parse(url)?;
+        if url.scheme() != "https" {
+            return Err(anyhow!("Only https URLs are fetched, not {}", url));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("{} has no host", url))?
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await?
+            .collect();
+        if addresses.is_empty() || !addresses.iter().all(|address| is_public_ip(address.ip())) {
+            return Err(anyhow!("{} does not resolve to a public address", host));
+        }
+        // The checked addresses are used for the request, so the host cannot be rebound
+        let http = reqwest::Client::builder()
+            .resolve_to_addrs(&host, &addresses)
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(FETCH_TIMEOUT)
+            .timeout(FETCH_TIMEOUT)
+            .build()?;
+        let mut response = http.get(url.clone()).send().await?.error_for_status()?;
+        if response
+            .content_length()
+            .map_or(false, |length| length > MAX_FETCH_BYTES as u64)
+        {
+            return Err(anyhow!("{} is larger than {} bytes", url, MAX_FETCH_BYTES));
+        }
+        let mut body = vec![];
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > MAX_FETCH_BYTES {
+                return Err(anyhow!("{} is larger than {} bytes", url, MAX_FETCH_BYTES));
+            }
+        }
+        Ok(String::from_utf8(body)?)
+    }
+
+    /// Resolves the key of a DID, `did:key` and `did:jwk` locally and `did:web` over HTTP.
+    pub async fn resolve_key(&self, did: &str, kid: Option<&str>) -> Result<DecodingKey> {
+        if did.starts_with("did:key:") {
+            return did_key_decoding_key(did);
+        }
+        if let Some(encoded) = did.strip_prefix("did:jwk:") {
+            // Holder keys of wallets, the JWK is part of the DID itself
+            let jwk: Jwk = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(encoded)?)?;
+            return Ok(DecodingKey::from_jwk(&jwk)?);
+        }
+        if !did.starts_with("did:web:") {
+            return Err(anyhow!("Unsupported DID method of {}", did));
+        }
+        if self.offline {
+            return Err(anyhow!("Resolving {} needs network access", did));
+        }
+        let document: Value = serde_json::from_str(&self.fetch(&did_web_url(did)?).await?)?;
+        let methods = document["verificationMethod"]
+            .as_array()
+            .ok_or_else(|| anyhow!("The DID document of {} has no verification method", did))?;
+        let method = match kid {
+            Some(kid) => methods.iter().find(|method| {
+                method["id"].as_str().map_or(false, |id| {
+                    id == kid || (kid.starts_with('#') && id.ends_with(kid))
+                })
+            }),
+            None => methods.first(),
+        }
+        .ok_or_else(|| anyhow!("Verification method {:?} not found for {}", kid, did))?;
+        verification_method_key(method)
+    }
+
+    /// Verifies the signature of a JWT signed with the key of its `iss` DID and returns its claims.
+    pub async fn verify_jwt(&self, jwt: &str) -> Result<Value> {
+        let header = decode_header(jwt)?;
+        if !matches!(header.alg, Algorithm::EdDSA | Algorithm::ES256) {
+            return Err(anyhow!("Unsupported algorithm {:?}", header.alg));
+        }
+        let claims = jwt_payload(jwt)?;
+        let issuer = claims["iss"]
+            .as_str()
+            .or_else(|| header.kid.as_deref().and_then(|kid| kid.split('#').next()))
+            .ok_or_else(|| anyhow!("The JWT does not name its issuer"))?
+            .to_string();
+        let key = self.resolve_key(&issuer, header.kid.as_deref()).await?;
+
+        // Expiry is reported as a check of its own
+        let mut validation = Validation::new(header.alg);
+        validation.set_required_spec_claims::<&str>(&[]);
+        validation.validate_exp = false;
+        validation.validate_nbf = false;
+        validation.validate_aud = false;
+        Ok(decode::<Value>(jwt, &key, &validation)?.claims)
+    }
+
+    async fn check_status_entry(&self, entry: &Value) -> Result<Option<String>> {
+        let entry_type = entry["type"].as_str().unwrap_or_default();
+        if !matches!(
+            entry_type,
+            "BitstringStatusListEntry" | "StatusList2021Entry"
+        ) {
+            return Err(anyhow!("Unsupported credential status type {}", entry_type));
+        }
+        let purpose = entry["statusPurpose"].as_str().unwrap_or("revocation");
+        let index: usize = match &entry["statusListIndex"] {
+            Value::String(index) => index.parse()?,
+            index => index
+                .as_u64()
+                .ok_or_else(|| anyhow!("Invalid statusListIndex"))? as usize,
+        };
+        let url = entry["statusListCredential"]
+            .as_str()
+            .ok_or_else(|| anyhow!("The credential status has no statusListCredential"))?;
+
+        let body = self.fetch(url).await?;
+        let status_list = match serde_json::from_str::<Value>(&body) {
+            Ok(status_list) => status_list,
+            Err(_) => self.verify_jwt(body.trim()).await?["vc"].clone(),
+        };
+        let encoded_list = status_list["credentialSubject"]["encodedList"]
+            .as_str()
+            .ok_or_else(|| anyhow!("The status list {} has no encodedList", url))?;
+        match status_list_bit(encoded_list, index)? {
+            true => Err(anyhow!("The credential's {} status is set", purpose)),
+            false => Ok(Some(format!("Not set in the {} status list", purpose))),
+        }
+    }
+
+    /// Revocation of this issuer's own credentials, also of those nested in a CLR, looked up in
+    /// its storage by the id of their verification page.
+    fn check_local_revocation(
+        &self,
+        issuer: Option<&str>,
+        credential: &Value,
+    ) -> Vec<Result<Option<String>>> {
+        let Some(storage) = &self.storage else {
+            return vec![];
+        };
+        if issuer != Some(get_issuer_did().as_str()) {
+            return vec![];
+        }
+        let nested = credential["credentialSubject"]["verifiableCredential"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        std::iter::once(credential)
+            .chain(nested)
+            .filter_map(public_credential_id)
+            .map(|credential_id| {
+                let credential =
+                    storage
+                        .get_public_credential(&credential_id)
+                        .ok_or_else(|| {
+                            anyhow!("Credential {} is unknown to the issuer", credential_id)
+                        })?;
+                match storage.get_revocation_date(&credential.certificate_id) {
+                    Some(revoked_at) => Err(anyhow!(
+                        "Credential {} was revoked on {}",
+                        credential_id,
+                        revoked_at.to_rfc3339()
+                    )),
+                    None => Ok(Some(format!("Credential {} is not revoked", credential_id))),
+                }
+            })
+            .collect()
+    }
+
+    /// Verifies a JWT VC and reports the outcome of each check.
+    pub async fn verify(&self, request: &VerificationRequest) -> VerificationReport {
+        if request.format != "jwt_vc_json" {
+            return VerificationReport::failed(
+                "format",
+                anyhow!("Unsupported credential format {}", request.format),
+            );
+        }
+        let claims = match self.verify_jwt(request.credential.trim()).await {
+            Ok(claims) => claims,
+            Err(e) => return VerificationReport::failed("signature", e),
+        };
+        let mut credential = claims["vc"].clone();
+        let mut report = VerificationReport {
+            issuer: claims["iss"].as_str().map(str::to_string),
+            subject: claims["sub"]
+                .as_str()
+                .or_else(|| credential["credentialSubject"]["id"].as_str())
+                .map(str::to_string),
+            credential_types: credential["type"]
+                .as_array()
+                .map(|types| {
+                    types
+                        .iter()
+                        .filter_map(|type_| type_.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        report.push("signature", Ok(None));
+        if !credential.is_object() {
+            report.push("credential", Err(anyhow!("The JWT has no vc claim")));
+            report.valid = false;
+            return report;
+        }
+
+        if let Some(nested) = credential["credentialSubject"]["verifiableCredential"].as_array_mut()
+        {
+            // Nested credentials of a CLR are JWTs signed on their own
+            for nested in nested.iter_mut().filter(|nested| nested.is_string()) {
+                let jwt = nested.as_str().unwrap_or_default().to_string();
+                let result = self.verify_jwt(&jwt).await.map(|claims| {
+                    *nested = claims["vc"].clone();
+                    None
+                });
+                report.push("nested", result);
+            }
+        }
+
+        let now = Utc::now();
+        let expires_at = claims["exp"]
+            .as_i64()
+            .and_then(|exp| DateTime::from_timestamp(exp, 0))
+            .into_iter()
+            .chain(parse_date(&credential["expirationDate"]))
+            .chain(parse_date(&credential["validUntil"]))
+            .min();
+        let valid_from = claims["nbf"]
+            .as_i64()
+            .and_then(|nbf| DateTime::from_timestamp(nbf, 0))
+            .into_iter()
+            .chain(parse_date(&credential["validFrom"]))
+            .max();
+        report.push(
+            "expiry",
+            match (valid_from, expires_at) {
+                (Some(valid_from), _) if valid_from > now => {
+                    Err(anyhow!("Not valid before {}", valid_from.to_rfc3339()))
+                }
+                (_, Some(expires_at)) if expires_at <= now => {
+                    Err(anyhow!("Expired at {}", expires_at.to_rfc3339()))
+                }
+                (_, Some(expires_at)) => {
+                    Ok(Some(format!("Valid until {}", expires_at.to_rfc3339())))
+                }
+                (_, None) => Ok(Some("Does not expire".to_string())),
+            },
+        );
+
+        let status_entries: Vec<Value> = match &credential["credentialStatus"] {
+            Value::Array(entries) => entries.clone(),
+            Value::Null => vec![],
+            entry => vec![entry.clone()],
+        };
+        let local_revocations = self.check_local_revocation(report.issuer.as_deref(), &credential);
+        if status_entries.is_empty() && local_revocations.is_empty() {
+            report.skip("revocation", "The credential has no credentialStatus");
+        } else if self.offline && !status_entries.is_empty() {
+            report.skip("revocation", "Status lists are not fetched offline");
+        }
+        for result in local_revocations {
+            report.push("revocation", result);
+        }
+        for entry in status_entries.iter().filter(|_| !self.offline) {
+            let result = self.check_status_entry(entry).await;
+            report.push("revocation", result);
+        }
+
+        let types = &report.credential_types;
+        let credential_configuration_id = if types.iter().any(|type_| type_ == "ClrCredential") {
+            Some(CLR_CREDENTIAL_CONFIGURATION_ID)
+        } else if types
+            .iter()
+            .any(|type_| type_ == "OpenBadgeCredential" || type_ == "AchievementCredential")
+        {
+            Some(CERTIFICATE_CREDENTIAL_CONFIGURATION_ID)
+        } else {
+            None
+        };
+        match credential_configuration_id {
+            Some(credential_configuration_id) => {
+                let result =
+                    validate_credential(credential_configuration_id, &credential).map(|_| None);
+                report.push("schema", result);
+            }
+            None => report.skip("schema", "Not an Open Badges or CLR credential"),
+        }
+
+        report.valid = report
+            .checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed);
+        report.credential = Some(credential);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer_key::{ed25519_key_from_secret, sign_with_key};
+    use did_key::DIDCore;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+    use std::io::Write;
+
+    #[test]
+    fn test_did_web_url() {
+        assert_eq!(
+            did_web_url("did:web:vc.konnektoren.help").unwrap(),
+            "https://vc.konnektoren.help/.well-known/did.json"
+        );
+        assert_eq!(
+            did_web_url("did:web:example.com%3A8443:schools:berlin").unwrap(),
+            "https://example.com:8443/schools/berlin/did.json"
+        );
+        assert!(did_web_url("did:key:z6Mk").is_err());
+    }
+
+    #[test]
+    fn test_status_list_bit() {
+        let mut bits = vec![0u8; 16];
+        bits[1] = 0b0010_0000;
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&bits).unwrap();
+        let encoded_list = format!("u{}", URL_SAFE_NO_PAD.encode(encoder.finish().unwrap()));
+
+        assert!(status_list_bit(&encoded_list, 10).unwrap());
+        assert!(!status_list_bit(&encoded_list, 11).unwrap());
+        assert!(status_list_bit(&encoded_list, 1000).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_did_key_credential() {
+        let key = ed25519_key_from_secret("this-is-a-very-UNSAFE-issuer-secret-key");
+        let did = key.get_did_document(did_key::Config::default()).id;
+        let credential: Value =
+            serde_json::from_str(include_str!("../assets/konnektoren_certificate.json")).unwrap();
+        let claims = json!({
+            "iss": did,
+            "sub": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+            "iat": 0,
+            "exp": 9999999999i64,
+            "vc": credential,
+        });
+        let jwt = sign_with_key(&key, "JWT", &claims).unwrap();
+
+        let verifier = CredentialVerifier::new();
+        let report = verifier
+            .verify(&VerificationRequest {
+                format: default_format(),
+                credential: jwt.clone(),
+            })
+            .await;
+        assert!(report.valid, "{:?}", report.checks);
+        assert_eq!(report.issuer, Some(did));
+        assert!(report
+            .checks
+            .iter()
+            .any(|check| check.check == "schema" && check.status == CheckStatus::Passed));
+
+        let report = CredentialVerifier::offline()
+            .verify(&VerificationRequest::jwt_vc(&jwt))
+            .await;
+        assert!(report.valid, "{:?}", report.checks);
+        assert!(report
+            .checks
+            .iter()
+            .any(|check| check.check == "revocation" && check.status == CheckStatus::Skipped));
+
+        let mut tampered = jwt;
+        tampered.insert(tampered.rfind('.').unwrap() + 1, 'A');
+        let report = verifier
+            .verify(&VerificationRequest {
+                format: default_format(),
+                credential: tampered,
+            })
+            .await;
+        assert!(!report.valid);
+    }
+
+    #[tokio::test]
+    async fn test_verify_clr_with_nested_credentials() {
+        let key = ed25519_key_from_secret("this-is-a-very-UNSAFE-issuer-secret-key");
+        let did = key.get_did_document(did_key::Config::default()).id;
+        let sign = |vc: Value| {
+            let claims = json!({ "iss": did, "iat": 0, "exp": 9999999999i64, "vc": vc });
+            sign_with_key(&key, "JWT", &claims).unwrap()
+        };
+        let certificate: Value =
+            serde_json::from_str(include_str!("../assets/konnektoren_certificate.json")).unwrap();
+        let achievement = certificate["credentialSubject"]["achievement"].clone();
+        let achievement_credential = sign(certificate);
+        let clr = |nested: &str| {
+            sign(json!({
+                "@context": [
+                    "https://www.w3.org/2018/credentials/v1",
+                    crate::clr_data::CLR_CONTEXT,
+                ],
+                "id": "urn:uuid:6b2e2b36-7a0f-4d2c-9a51-0b0c3c1c4b8e",
+                "type": ["VerifiableCredential", "ClrCredential"],
+                "name": "Learner record",
+                "issuer": { "id": "https://vc.konnektoren.help/ob3/issuer", "type": ["Profile"] },
+                "issuanceDate": "2024-09-30T08:00:00Z",
+                "credentialSubject": {
+                    "id": "did:example:ebfeb1f712ebc6f1c276e12ec21",
+                    "type": ["ClrSubject"],
+                    "verifiableCredential": [nested],
+                    "achievement": [achievement],
+                },
+            }))
+        };
+
+        let verifier = CredentialVerifier::offline();
+        let report = verifier
+            .verify(&VerificationRequest::jwt_vc(&clr(&achievement_credential)))
+            .await;
+        assert!(report.valid, "{:?}", report.checks);
+        let nested = &report.credential.unwrap()["credentialSubject"]["verifiableCredential"][0];
+        assert!(nested["credentialSubject"].is_object());
+
+        let mut tampered = achievement_credential;
+        tampered.insert(tampered.rfind('.').unwrap() + 1, 'A');
+        let report = verifier
+            .verify(&VerificationRequest::jwt_vc(&clr(&tampered)))
+            .await;
+        assert!(!report.valid);
+        assert!(report
+            .checks
+            .iter()
+            .any(|check| check.check == "nested" && check.status == CheckStatus::Failed));
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_only_from_public_https_urls() {
+        let verifier = CredentialVerifier::new();
+        assert!(verifier.fetch("http://example.com/status").await.is_err());
+        assert!(verifier.fetch("https://127.0.0.1/status").await.is_err());
+        assert!(verifier
+            .fetch("https://169.254.169.254/latest/meta-data")
+            .await
+            .is_err());
+        assert!(verifier
+            .resolve_key("did:web:localhost", None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_revocation_of_own_credentials() {
+        crate::config::set_test_env();
+        let storage = MemoryStorage::new();
+        let certificate: crate::certificate_data::CertificateData = serde_json::from_value(json!({
+            "game_path_name": "Introduction to Rust",
+            "total_challenges": 10,
+            "solved_challenges": 8,
+            "performance_percentage": 80,
+            "profile_name": "Alice Rustacean",
+            "profile_id": "alice",
+            "date": "2024-09-30T08:00:00Z",
+        }))
+        .unwrap();
+        storage.store_certificate("certificate-1".to_string(), certificate.clone());
+        let signer = std::sync::Arc::new(
+            oid4vc_manager::methods::key_method::KeySubject::from_keypair(
+                crate::issuer_key::issuer_key(),
+                None,
+            ),
+        );
+        let Some(oid4vci::credential_response::CredentialResponseType::Immediate {
+            credential,
+            ..
+        }) = storage.sign_certificate(
+            "token-1",
+            "certificate-1",
+            certificate,
+            "did:key:z6MkiTBz1ymuepAQ4HEHYSF1H8quG5GLVVQR3djdX3mDooWp"
+                .parse()
+                .unwrap(),
+            get_issuer_did(),
+            signer,
+        )
+        else {
+            panic!("The certificate was not issued");
+        };
+        let request = VerificationRequest::jwt_vc(credential.as_str().unwrap());
+        let revocation = |report: &VerificationReport| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.check == "revocation")
+                .map(|check| check.status)
+        };
+
+        let verifier = CredentialVerifier::offline().with_storage(storage.clone());
+        let report = verifier.verify(&request).await;
+        assert!(report.valid, "{:?}", report.checks);
+        assert_eq!(revocation(&report), Some(CheckStatus::Passed));
+
+        storage.revoke_certificate("certificate-1");
+        let report = verifier.verify(&request).await;
+        assert!(!report.valid);
+        assert_eq!(revocation(&report), Some(CheckStatus::Failed));
+
+        // Without the issuer's storage, the revocation cannot be checked
+        let report = CredentialVerifier::offline().verify(&request).await;
+        assert_eq!(revocation(&report), Some(CheckStatus::Skipped));
+    }
+}
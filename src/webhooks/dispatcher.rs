@@ -0,0 +1,335 @@
+use super::{WebhookEndpoint, WebhookEvent, WebhookEventType};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+const MAX_DELIVERY_LOG_ENTRIES: usize = 1000;
+
+pub const SIGNATURE_HEADER: &str = "X-Konnektoren-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Konnektoren-Timestamp";
+pub const EVENT_HEADER: &str = "X-Konnektoren-Event";
+pub const DELIVERY_HEADER: &str = "X-Konnektoren-Delivery";
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff: 1x, 2x, 4x, ... the initial backoff.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub event_id: String,
+    pub event_type: WebhookEventType,
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct DeliveryFilter {
+    pub event_type: Option<WebhookEventType>,
+    pub status: Option<DeliveryStatus>,
+}
+
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    endpoints: Arc<Vec<WebhookEndpoint>>,
+    client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    deliveries: Arc<Mutex<Vec<WebhookDelivery>>>,
+    tasks: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>, // deliveries still running
+}
+
+impl WebhookDispatcher {
+    pub fn new(endpoints: Vec<WebhookEndpoint>) -> Self {
+        Self::with_retry_policy(endpoints, RetryPolicy::default())
+    }
+
+    pub fn with_retry_policy(endpoints: Vec<WebhookEndpoint>, retry_policy: RetryPolicy) -> Self {
+        Self {
+            endpoints: Arc::new(endpoints),
+            client: reqwest::Client::new(),
+            retry_policy,
+            deliveries: Arc::new(Mutex::new(Vec::new())),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Sends the event to every subscribed endpoint in the background.
+    #[instrument(skip_all)]
+    pub fn dispatch(&self, event: WebhookEvent) {
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => {
+                log::warn!("No runtime available, dropping webhook event {}", event.id);
+                return;
+            }
+        };
+
+        for endpoint in self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.accepts(event.event_type))
+        {
+            let delivery_id = self.record_delivery(&event, endpoint);
+            let dispatcher = self.clone();
+            let endpoint = endpoint.clone();
+            let event = event.clone();
+            let task = handle.spawn(async move {
+                dispatcher.deliver(delivery_id, endpoint, event).await;
+            });
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(task);
+        }
+    }
+
+    /// Waits until the events dispatched so far are delivered or given up on, e.g. before the
+    /// command line exits.
+    pub async fn finish(&self) {
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in tasks {
+            if let Err(e) = task.await {
+                log::error!("Webhook delivery failed: {}", e);
+            }
+        }
+    }
+
+    async fn deliver(&self, delivery_id: String, endpoint: WebhookEndpoint, event: WebhookEvent) {
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("Failed to serialize webhook event {}: {}", event.id, e);
+                return;
+            }
+        };
+
+        for attempt in 1..=self.retry_policy.max_attempts {
+            let timestamp = Utc::now().timestamp();
+            let result = self
+                .client
+                .post(endpoint.url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.event_type.as_str())
+                .header(DELIVERY_HEADER, &delivery_id)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(
+                    SIGNATURE_HEADER,
+                    sign_payload(&endpoint.secret, timestamp, &body),
+                )
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (status_code, error) = match result {
+                Ok(response) if response.status().is_success() => {
+                    self.update_delivery(&delivery_id, |delivery| {
+                        delivery.status = DeliveryStatus::Delivered;
+                        delivery.attempts = attempt;
+                        delivery.last_status_code = Some(response.status().as_u16());
+                        delivery.last_error = None;
+                    });
+                    log::info!("Delivered webhook {} to {}", event.id, endpoint.url);
+                    return;
+                }
+                Ok(response) => (
+                    Some(response.status().as_u16()),
+                    format!("Unexpected status {}", response.status()),
+                ),
+                Err(e) => (None, e.to_string()),
+            };
+
+            log::warn!(
+                "Webhook delivery {} to {} failed (attempt {}/{}): {}",
+                delivery_id,
+                endpoint.url,
+                attempt,
+                self.retry_policy.max_attempts,
+                error
+            );
+            let exhausted = attempt == self.retry_policy.max_attempts;
+            self.update_delivery(&delivery_id, |delivery| {
+                delivery.attempts = attempt;
+                delivery.last_status_code = status_code;
+                delivery.last_error = Some(error);
+                if exhausted {
+                    delivery.status = DeliveryStatus::Failed;
+                }
+            });
+
+            if !exhausted {
+                tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            }
+        }
+    }
+
+    fn record_delivery(&self, event: &WebhookEvent, endpoint: &WebhookEndpoint) -> String {
+        let now = Utc::now();
+        let delivery = WebhookDelivery {
+            id: Uuid::new_v4().to_string(),
+            event_id: event.id.clone(),
+            event_type: event.event_type,
+            url: endpoint.url.to_string(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_status_code: None,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let id = delivery.id.clone();
+
+        let mut deliveries = self.deliveries.lock().unwrap();
+        if deliveries.len() >= MAX_DELIVERY_LOG_ENTRIES {
+            deliveries.remove(0);
+        }
+        deliveries.push(delivery);
+        id
+    }
+
+    fn update_delivery(&self, delivery_id: &str, update: impl FnOnce(&mut WebhookDelivery)) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        if let Some(delivery) = deliveries.iter_mut().find(|d| d.id == delivery_id) {
+            update(delivery);
+            delivery.updated_at = Utc::now();
+        }
+    }
+
+    /// Returns the delivery log, newest first.
+    pub fn deliveries(&self, filter: &DeliveryFilter) -> Vec<WebhookDelivery> {
+        let deliveries = self.deliveries.lock().unwrap();
+        deliveries
+            .iter()
+            .rev()
+            .filter(|d| filter.event_type.map_or(true, |t| d.event_type == t))
+            .filter(|d| filter.status.map_or(true, |s| d.status == s))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Signs `{timestamp}.{body}` with HMAC-SHA256, formatted as `sha256=<hex>`.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use hyper::body::Bytes;
+    use serde_json::json;
+
+    #[derive(Clone, Default)]
+    struct StandIn {
+        received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    }
+
+    async fn receive(
+        State(stand_in): State<StandIn>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        let mut received = stand_in.received.lock().unwrap();
+        received.push((headers, body));
+        // Fail the first attempt to exercise the retry path
+        if received.len() == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_retried() {
+        let stand_in = StandIn::default();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url: url::Url = format!("http://{}/hooks", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let app = Router::new()
+            .route("/hooks", post(receive))
+            .with_state(stand_in.clone());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let dispatcher = WebhookDispatcher::with_retry_policy(
+            vec![WebhookEndpoint {
+                url,
+                secret: "lms-secret".to_string(),
+                events: vec![WebhookEventType::CredentialIssued],
+            }],
+            RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(10),
+            },
+        );
+
+        dispatcher.dispatch(WebhookEvent::new(
+            WebhookEventType::OfferCreated,
+            json!({ "certificate_id": "ignored" }),
+        ));
+        dispatcher.dispatch(WebhookEvent::new(
+            WebhookEventType::CredentialIssued,
+            json!({ "certificate_id": "1234" }),
+        ));
+
+        dispatcher.finish().await;
+
+        let deliveries = dispatcher.deliveries(&DeliveryFilter::default());
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Delivered);
+        assert_eq!(deliveries[0].attempts, 2);
+
+        let received = stand_in.received.lock().unwrap();
+        let (headers, body) = received.last().unwrap();
+        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign_payload("lms-secret", timestamp, body)
+        );
+        assert_eq!(headers[EVENT_HEADER].to_str().unwrap(), "credential.issued");
+    }
+}
@@ -0,0 +1,41 @@
+use super::WebhookEventType;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{env, fs::File, path::Path};
+use url::Url;
+
+/// A receiver of webhook notifications, e.g. the LMS backend.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookEndpoint {
+    pub url: Url,
+    pub secret: String,
+    /// Events this endpoint is subscribed to. An empty list subscribes to all events.
+    #[serde(default)]
+    pub events: Vec<WebhookEventType>,
+}
+
+impl WebhookEndpoint {
+    pub fn accepts(&self, event_type: WebhookEventType) -> bool {
+        self.events.is_empty() || self.events.contains(&event_type)
+    }
+}
+
+/// Loads the webhook endpoints from the JSON file referenced by `WEBHOOKS_FILE`.
+/// No webhooks are sent if the variable is not set or the file does not exist.
+pub fn load_webhook_endpoints() -> Result<Vec<WebhookEndpoint>> {
+    match env::var("WEBHOOKS_FILE") {
+        Ok(path) if !Path::new(&path).exists() => {
+            log::warn!(
+                "Webhooks file {} does not exist, no webhooks are sent",
+                path
+            );
+            Ok(vec![])
+        }
+        Ok(path) => {
+            let endpoints: Vec<WebhookEndpoint> = serde_json::from_reader(File::open(&path)?)?;
+            log::info!("Loaded {} webhook endpoints from {}", endpoints.len(), path);
+            Ok(endpoints)
+        }
+        Err(_) => Ok(vec![]),
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WebhookEventType {
+    #[serde(rename = "offer.created")]
+    OfferCreated,
+    #[serde(rename = "token.issued")]
+    TokenIssued,
+    #[serde(rename = "credential.issued")]
+    CredentialIssued,
+    #[serde(rename = "credential.revoked")]
+    CredentialRevoked,
+    #[serde(rename = "offer.expired")]
+    OfferExpired,
+    #[serde(rename = "presentation.completed")]
+    PresentationCompleted,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::OfferCreated => "offer.created",
+            WebhookEventType::TokenIssued => "token.issued",
+            WebhookEventType::CredentialIssued => "credential.issued",
+            WebhookEventType::CredentialRevoked => "credential.revoked",
+            WebhookEventType::OfferExpired => "offer.expired",
+            WebhookEventType::PresentationCompleted => "presentation.completed",
+        }
+    }
+}
+
+/// Payload that is posted to the configured webhook endpoints.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub created_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: WebhookEventType, data: Value) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            event_type,
+            created_at: Utc::now(),
+            data,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod dispatcher;
+mod endpoint;
+mod event;
+
+pub use dispatcher::*;
+pub use endpoint::*;
+pub use event::*;